@@ -38,11 +38,14 @@ exists without a directory <s>foo</s>.
 
 When adding <i>foo</i> directory recursively, both <i>foo</i> and <i>foo/bar.txt</i> are added
 using attribute from filesystem metadata (stats on Linux)
-When directly adding <i>foo/bar.txt</i>, <i>foo</i> directory is automatically created using default
-metadata:
-- owner and group: 1000,
-- right: 0x755,
-- mtime: 0
+When directly adding <i>foo/bar.txt</i>, <i>foo</i> directory is automatically created.
+Its metadata depends on <i>--implicit-dirs</i>:
+- <i>fixed</i> (default): owner and group 1000, rights 0o755 and mtime 0
+  (change them with <i>--implicit-dir-owner</i>, <i>--implicit-dir-group</i>,
+  <i>--implicit-dir-mode</i> and <i>--implicit-dir-mtime</i>),
+- <i>inherit</i>: owner, group and mtime of <i>foo/bar.txt</i> (rights are made searchable),
+- <i>filesystem</i>: metadata of <i>foo</i> on the filesystem (fixed values if it doesn't exist),
+- <i>current-user</i>: current user and group, rights 0o755 and mtime 0.
 
 This is the same thing when using file list. It is preferable to list both directories and files in the listing:
 ```
@@ -57,6 +60,16 @@ Listing in file list and <i>--follow-symlink</i> passed to arx, you must be cohe
 Follow symlink all the time or never.
 If you don't, you may have incoherent state when symlink are pointing to directories.
 
+<s,u>Reproducible archives:</>
+
+Arx honours the <i>SOURCE_DATE_EPOCH</i> environment variable: modification times later than it
+are set to it and access/change/birth times are not stored.
+Use <i>--reproducible</i> to also not store access/change/birth times without <i>SOURCE_DATE_EPOCH</i>
+and to store owner and group as 0 (unless <i>--owner</i>/<i>--group</i> are given).
+Entries are always added in a deterministic order.
+Archive files are not byte for byte identical however: Jubako identifies the packs with random
+UUIDs.
+
 <s,u>Compression detection/selection:</>
 
 Arx automatically detect if a content should be compressed or not based on a heuristic using
@@ -164,6 +177,12 @@ pub struct Options {
     #[arg(short, long, required = false, default_value_t = false, action)]
     force: bool,
 
+    /// Compute and store a checksum of the content of each file.
+    ///
+    /// Checksums are used by `arx verify` to detect corrupted content.
+    #[arg(long, default_value_t = false, action)]
+    checksum: bool,
+
     /// Follow symbolic link found in the input files
     #[arg(
         long,
@@ -174,10 +193,85 @@ pub struct Options {
     )]
     follow_symlink: bool,
 
+    /// Do not add files matching PATTERN (gitignore syntax).
+    ///
+    /// Excluding a directory excludes all its content.
+    /// May be given several times.
+    #[arg(long, value_name = "PATTERN", help_heading = "Input options")]
+    exclude: Vec<String>,
+
+    /// Read exclude patterns from FILE (one per line, gitignore syntax).
+    #[arg(long, value_name = "FILE", value_hint=ValueHint::FilePath, help_heading = "Input options")]
+    exclude_from: Vec<PathBuf>,
+
+    /// Exclude version control system directories and files (`.git`, `.svn`, ...).
+    #[arg(long, help_heading = "Input options")]
+    exclude_vcs: bool,
+
+    /// Honour the `.gitignore` and `.ignore` files found in the added directories.
+    ///
+    /// This is used only when walking directories (not for the entries listed in FILE_LIST).
+    #[arg(long, help_heading = "Input options")]
+    respect_gitignore: bool,
+
+    /// Create a reproducible archive.
+    ///
+    /// Access, status change and birth times are not stored and owner and group default to 0.
+    /// Combined with `SOURCE_DATE_EPOCH`, creating an archive twice from the same input
+    /// gives the same entries and contents. The archive files still differ by the UUIDs of
+    /// their packs, which are randomly generated by Jubako.
+    #[arg(long, help_heading = "Metadata")]
+    reproducible: bool,
+
+    /// Store OWNER (numeric id) as owner of all entries.
+    #[arg(long, help_heading = "Metadata")]
+    owner: Option<u64>,
+
+    /// Store GROUP (numeric id) as group of all entries.
+    #[arg(long, help_heading = "Metadata")]
+    group: Option<u64>,
+
+    /// Clear the bits of MODE_MASK (octal, as a umask) from the rights of all entries.
+    #[arg(long, value_parser = parse_octal, help_heading = "Metadata")]
+    mode_mask: Option<u64>,
+
+    /// How to set the metadata of directories created implicitly (see below).
+    #[arg(long, value_enum, default_value = "fixed", help_heading = "Metadata")]
+    implicit_dirs: ImplicitDirs,
+
+    /// Owner (numeric id) of the directories created implicitly with `--implicit-dirs fixed`.
+    #[arg(long, value_name = "OWNER", help_heading = "Metadata")]
+    implicit_dir_owner: Option<u64>,
+
+    /// Group (numeric id) of the directories created implicitly with `--implicit-dirs fixed`.
+    #[arg(long, value_name = "GROUP", help_heading = "Metadata")]
+    implicit_dir_group: Option<u64>,
+
+    /// Rights (octal) of the directories created implicitly with `--implicit-dirs fixed`.
+    #[arg(long, value_name = "MODE", value_parser = parse_octal, help_heading = "Metadata")]
+    implicit_dir_mode: Option<u64>,
+
+    /// Modification time (seconds since epoch) of the directories created implicitly with
+    /// `--implicit-dirs fixed`.
+    #[arg(long, value_name = "MTIME", help_heading = "Metadata")]
+    implicit_dir_mtime: Option<u64>,
+
     #[arg(from_global)]
     verbose: u8,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ImplicitDirs {
+    Fixed,
+    Inherit,
+    Filesystem,
+    CurrentUser,
+}
+
+fn parse_octal(s: &str) -> Result<u64> {
+    u64::from_str_radix(s, 8).with_context(|| format!("Invalid octal value {s}"))
+}
+
 fn check_input_paths_exist(file_list: &[PathBuf]) -> Result<()> {
     // Check that input files actually exists
     for file in file_list.iter() {
@@ -274,6 +368,20 @@ pub fn create(options: Options) -> Result<()> {
         return Ok(());
     }
 
+    let fixed_values = [
+        options.implicit_dir_owner,
+        options.implicit_dir_group,
+        options.implicit_dir_mode,
+        options.implicit_dir_mtime,
+    ];
+    if !matches!(options.implicit_dirs, ImplicitDirs::Fixed)
+        && fixed_values.iter().any(Option::is_some)
+    {
+        return Err(anyhow!(
+            "--implicit-dir-owner, --implicit-dir-group, --implicit-dir-mode and --implicit-dir-mtime can only be used with `--implicit-dirs fixed`"
+        ));
+    }
+
     let strip_prefix = match &options.strip_prefix {
         Some(s) => s.clone(),
         None => arx::PathBuf::new(),
@@ -291,9 +399,23 @@ pub fn create(options: Options) -> Result<()> {
         .as_ref()
         .map(std::path::absolute)
         .transpose()?;
+    let mut exclude_builder = arx::create::ExcludeFilterBuilder::new();
+    for pattern in &options.exclude {
+        exclude_builder.add_pattern(pattern)?;
+    }
+    for exclude_file in &options.exclude_from {
+        exclude_builder
+            .add_patterns_from_file(exclude_file)
+            .with_context(|| format!("Cannot read {}", exclude_file.display()))?;
+    }
+    if options.exclude_vcs {
+        exclude_builder.exclude_vcs();
+    }
+    exclude_builder.respect_gitignore(options.respect_gitignore);
     if let Some(base_dir) = &options.base_dir {
         std::env::set_current_dir(base_dir)?;
     };
+    let exclude_filter = exclude_builder.build()?;
 
     let jbk_progress: Arc<dyn jbk::creator::Progress> = if options.progress {
         Arc::new(ProgressBar::new())
@@ -312,14 +434,43 @@ pub fn create(options: Options) -> Result<()> {
         options.compression,
     )?;
 
+    let default_id = if options.reproducible { Some(0) } else { None };
+    let mtime_clamp = arx::create::MetadataOverride::source_date_epoch()?;
+    creator.set_metadata_override(arx::create::MetadataOverride {
+        owner: options.owner.or(default_id),
+        group: options.group.or(default_id),
+        mode_mask: options.mode_mask.unwrap_or(0),
+        mtime_clamp,
+        // Other times would make the archive depend on when it is created.
+        drop_extra_times: options.reproducible || mtime_clamp.is_some(),
+    });
+    creator.set_implicit_dir_policy(match options.implicit_dirs {
+        ImplicitDirs::Fixed => arx::create::ImplicitDirPolicy::Fixed {
+            owner: options.implicit_dir_owner.unwrap_or(1000),
+            group: options.implicit_dir_group.unwrap_or(1000),
+            mode: options.implicit_dir_mode.unwrap_or(0o755),
+            mtime: options.implicit_dir_mtime.unwrap_or(0),
+        },
+        ImplicitDirs::Inherit => arx::create::ImplicitDirPolicy::InheritFromChild,
+        ImplicitDirs::Filesystem => arx::create::ImplicitDirPolicy::FromFilesystem(
+            strip_prefix.to_path(std::env::current_dir()?),
+        ),
+        ImplicitDirs::CurrentUser => arx::create::ImplicitDirPolicy::CurrentUser,
+    });
+
     if let Some(file_list) = file_list {
         let file = File::open(&file_list)
             .with_context(|| format!("Cannot open {}", file_list.display()))?;
         let files_list = BufReader::new(file)
             .lines()
             .map(|l| -> Result<PathBuf> { Ok(l?.into()) })
+            .filter(|p| match p {
+                Ok(p) => !exclude_filter.is_excluded(p, p.is_dir()),
+                Err(_) => true,
+            })
             .collect::<Result<Vec<_>>>()?;
         let mut list_adder = arx::create::FsAdder::new(&mut creator, strip_prefix);
+        list_adder.set_checksum(options.checksum);
         list_adder.add_from_list(files_list.into_iter(), options.follow_symlink)?;
     } else {
         let files_list = options
@@ -335,9 +486,12 @@ pub fn create(options: Options) -> Result<()> {
             .collect::<Result<Vec<_>>>()?;
         check_input_paths_exist(&files_list)?;
         let mut fs_adder = arx::create::FsAdder::new(&mut creator, strip_prefix);
+        fs_adder.set_checksum(options.checksum);
         for infile in files_list {
             debug!("Adding file {infile:?}");
-            fs_adder.add_from_path(&infile, options.recurse)?;
+            let mut filter = exclude_filter.clone();
+            fs_adder
+                .add_from_path_with_filter(&infile, options.recurse, move |e| filter.keep(e))?;
         }
     };
 
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use arx::diff::{Change, Difference};
+use clap::{Parser, ValueHint};
+use log::{error, info};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Compare an archive with another archive or a directory.
+///
+/// Print the entries added, removed, whose type changed and whose metadata
+/// (mode, owner, group, mtime) or content changed.
+/// Like diff(1), exit with 0 if there is no difference, 1 if differences are found
+/// and 2 on errors.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Old archive
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    old: PathBuf,
+
+    /// New archive or directory
+    #[arg(value_parser, value_hint=ValueHint::AnyPath)]
+    new: PathBuf,
+
+    /// Compare only the type and content of the entries.
+    #[arg(long, default_value_t = false, action)]
+    ignore_metadata: bool,
+
+    /// Do not compare modification times.
+    #[arg(long, default_value_t = false, action)]
+    ignore_mtime: bool,
+
+    /// Print the differences as JSON.
+    #[arg(long, default_value_t = false, action)]
+    json: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+fn change_to_json(change: &Change) -> Value {
+    match change {
+        Change::Mode { old, new } => json!({"field": "mode", "old": old, "new": new}),
+        Change::Owner { old, new } => json!({"field": "owner", "old": old, "new": new}),
+        Change::Group { old, new } => json!({"field": "group", "old": old, "new": new}),
+        Change::Mtime { old, new } => json!({
+            "field": "mtime",
+            "old": [old.0, old.1],
+            "new": [new.0, new.1]
+        }),
+        Change::Size { old, new } => json!({"field": "size", "old": old, "new": new}),
+        Change::LinkTarget { old, new } => json!({
+            "field": "link_target",
+            "old": String::from_utf8_lossy(old),
+            "new": String::from_utf8_lossy(new)
+        }),
+        Change::Device { old, new } => json!({
+            "field": "device",
+            "old": [old.0, old.1],
+            "new": [new.0, new.1]
+        }),
+        Change::Content => json!({"field": "content"}),
+    }
+}
+
+fn difference_to_json(difference: &Difference) -> Value {
+    let path = difference.path().as_str();
+    match difference {
+        Difference::Added { .. } => json!({"status": "added", "path": path}),
+        Difference::Removed { .. } => json!({"status": "removed", "path": path}),
+        Difference::TypeChanged { old, new, .. } => {
+            json!({"status": "type_changed", "path": path, "old": old, "new": new})
+        }
+        Difference::Modified { changes, .. } => json!({
+            "status": "modified",
+            "path": path,
+            "changes": changes.iter().map(change_to_json).collect::<Vec<_>>()
+        }),
+    }
+}
+
+/// Print the differences.
+///
+/// Return true if there are differences.
+fn compare(options: Options) -> Result<bool> {
+    info!("Compare {:?} with {:?}", options.old, options.new);
+    let diff_options = arx::diff::DiffOptions {
+        ignore_metadata: options.ignore_metadata,
+        ignore_mtime: options.ignore_mtime,
+    };
+    let old = arx::Arx::new(&options.old).with_context(|| format!("Opening {:?}", options.old))?;
+    let differences = if options.new.is_dir() {
+        arx::diff::diff_archive_dir(&old, &options.new, &diff_options)?
+    } else {
+        let new =
+            arx::Arx::new(&options.new).with_context(|| format!("Opening {:?}", options.new))?;
+        arx::diff::diff_archives(&old, &new, &diff_options)?
+    };
+
+    if options.json {
+        let differences: Vec<_> = differences.iter().map(difference_to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&differences)?);
+    } else {
+        for difference in &differences {
+            println!("{difference}");
+        }
+    }
+    Ok(!differences.is_empty())
+}
+
+pub fn diff(options: Options) -> ExitCode {
+    match compare(options) {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(e) => {
+            error!("Error : {e:#}");
+            ExitCode::from(2)
+        }
+    }
+}
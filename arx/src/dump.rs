@@ -47,6 +47,7 @@ fn dump_entry(
             Ok(())
         }
         arx::Entry::Link(_) => Err("Found link".to_string().into()),
+        arx::Entry::Special(_) => Err("Found special file".to_string().into()),
     }
 }
 
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Tar,
+    Zip,
+}
+
+impl Format {
+    /// Detect the format from the extension of `path`.
+    fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("zip") => Self::Zip,
+            _ => Self::Tar,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression from the extension of `path`.
+    fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz" | "tgz") => Self::Gzip,
+            Some("bz2" | "tbz2") => Self::Bzip2,
+            Some("xz" | "txz") => Self::Xz,
+            Some("zst" | "tzst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+
+    fn wrap(self, writer: Box<dyn Write>) -> Result<Box<dyn Write>> {
+        let format = match self {
+            Self::None => return Ok(writer),
+            Self::Gzip => niffler::compression::Format::Gzip,
+            Self::Bzip2 => niffler::compression::Format::Bzip,
+            Self::Xz => niffler::compression::Format::Lzma,
+            Self::Zstd => niffler::compression::Format::Zstd,
+        };
+        Ok(niffler::get_writer(
+            writer,
+            format,
+            niffler::compression::Level::Six,
+        )?)
+    }
+}
+
+/// Export an archive in another archive format.
+///
+/// The exported archive is written to OUTFILE or, for tar, to the standard output.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to export
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    /// File to write. Standard output is used if not given or "-".
+    #[arg(short, long, value_hint=ValueHint::FilePath)]
+    outfile: Option<PathBuf>,
+
+    /// Format of the exported archive.
+    ///
+    /// By default, zip is used if OUTFILE ends with `.zip`, tar else.
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Compress the exported tar.
+    ///
+    /// By default, the compression is detected from the extension of OUTFILE.
+    #[arg(long, value_enum)]
+    compress: Option<Compression>,
+
+    /// Export only the content of ROOT_DIR.
+    #[arg(long, value_hint=ValueHint::DirPath)]
+    root_dir: Option<PathBuf>,
+
+    /// Overwrite OUTFILE if it exists.
+    #[arg(short, long, required = false, default_value_t = false, action)]
+    force: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn export(options: Options) -> Result<()> {
+    info!("Export archive {:?}", options.infile);
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    let root_dir = options
+        .root_dir
+        .as_ref()
+        .map(arx::PathBuf::from_path)
+        .transpose()?;
+
+    let outfile = options.outfile.filter(|p| p != Path::new("-"));
+    let format = options.format.unwrap_or_else(|| match &outfile {
+        Some(outfile) => Format::from_extension(outfile),
+        None => Format::Tar,
+    });
+    if let Some(outfile) = &outfile {
+        if outfile.exists() && !options.force {
+            return Err(anyhow!(
+                "File {} already exists. Use option --force to overwrite it.",
+                outfile.display()
+            ));
+        }
+    }
+
+    if let Format::Zip = format {
+        let outfile = outfile.ok_or_else(|| anyhow!("A zip can only be written to a file"))?;
+        if options.compress.is_some_and(|c| c != Compression::None) {
+            return Err(anyhow!("Option --compress is only supported for tar"));
+        }
+        let file = std::fs::File::create(&outfile)
+            .with_context(|| format!("Creating {}", outfile.display()))?;
+        let mut writer =
+            arx::export::export_zip(&arx, root_dir.as_deref(), std::io::BufWriter::new(file))?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    let (writer, detected_compression): (Box<dyn Write>, _) = match &outfile {
+        None => (
+            Box::new(std::io::BufWriter::new(std::io::stdout().lock())),
+            Compression::None,
+        ),
+        Some(outfile) => {
+            let file = std::fs::File::create(outfile)
+                .with_context(|| format!("Creating {}", outfile.display()))?;
+            (
+                Box::new(std::io::BufWriter::new(file)),
+                Compression::from_extension(outfile),
+            )
+        }
+    };
+    let writer = options
+        .compress
+        .unwrap_or(detected_compression)
+        .wrap(writer)?;
+    let mut writer = arx::export::export_tar(&arx, root_dir.as_deref(), writer)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -62,6 +62,80 @@ pub struct Options {
     #[arg(long)]
     no_recurse: bool,
 
+    /// Restore the rights of extracted files and directories.
+    #[arg(long, help_heading = "Metadata")]
+    preserve_mode: bool,
+
+    /// Restore the modification time of extracted files, directories and symlinks.
+    ///
+    /// Access time is also restored if it is stored in the archive.
+    #[arg(long, help_heading = "Metadata")]
+    preserve_mtime: bool,
+
+    /// Restore the owner and group (numeric ids) of extracted entries.
+    ///
+    /// This is possible only when running as root.
+    #[arg(long, help_heading = "Metadata")]
+    preserve_owner: bool,
+
+    /// Restore the extended attributes of extracted entries.
+    #[arg(long, help_heading = "Metadata")]
+    preserve_xattrs: bool,
+
+    /// Restore all metadata.
+    ///
+    /// Same as `--preserve-mode --preserve-mtime --preserve-owner --preserve-xattrs`.
+    #[arg(short = 'P', long, help_heading = "Metadata")]
+    preserve_all: bool,
+
+    /// Continue extracting other entries when an entry fails to be extracted.
+    ///
+    /// All failing entries are reported at the end.
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// What to do when an entry to extract already exists.
+    ///
+    /// `keep-newer` overwrites the existing file only if it is older than the entry.
+    /// `rename` renames the existing file as `name.~N~` before extracting the entry.
+    #[arg(long, value_enum, default_value = "error")]
+    overwrite: arx::OverwritePolicy,
+
+    /// Disable safe extraction.
+    ///
+    /// By default, entries with an unsafe name (`..`, absolute path, ...) are rejected
+    /// and nothing is written through a symlink.
+    /// Use this only with trusted archives.
+    #[arg(long)]
+    allow_unsafe_paths: bool,
+
+    /// Remove the N leading components of the path of the entries.
+    ///
+    /// Entries with fewer components are not extracted.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    strip_components: usize,
+
+    /// Transform the path of the entries (after --strip-components).
+    ///
+    /// EXPR is either `s/REGEX/REPLACEMENT/[g]` or `FROM=TO` to replace the leading components
+    /// FROM by TO. May be given several times, transformations are applied in order.
+    /// Entries to extract are still selected with their path in the archive.
+    #[arg(long = "transform", value_name = "EXPR")]
+    transforms: Vec<arx::PathTransform>,
+
+    /// Extract only entries matching the glob PATTERN (`**/*.so`, `docs/**`, ...)
+    ///
+    /// May be given several times. Patterns are matched against the path in the archive.
+    /// `*` doesn't match a `/`, use `**` to match across directories.
+    #[arg(long = "include", value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// Do not extract entries matching the glob PATTERN.
+    ///
+    /// Excluding a directory excludes all its content. May be given several times.
+    #[arg(long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+
     #[arg(
         short = 'f',
         long = "file",
@@ -100,6 +174,18 @@ pub fn extract(options: Options) -> anyhow::Result<()> {
     };
     let arx = arx::Arx::new(infile)?;
     info!("Extract archive {:?} in {:?}", &infile, outdir);
+    let extract_options = arx::ExtractOptions {
+        preserve_mode: options.preserve_mode || options.preserve_all,
+        preserve_mtime: options.preserve_mtime || options.preserve_all,
+        preserve_owner: options.preserve_owner || options.preserve_all,
+        preserve_xattrs: options.preserve_xattrs || options.preserve_all,
+        continue_on_error: options.continue_on_error,
+        overwrite: options.overwrite,
+        allow_unsafe_paths: options.allow_unsafe_paths,
+        strip_components: options.strip_components,
+        transforms: options.transforms.clone(),
+        filter: arx::PathFilter::new(&options.include, &options.exclude)?,
+    };
 
     match options.root_dir {
         None => arx::extract_arx(
@@ -108,6 +194,7 @@ pub fn extract(options: Options) -> anyhow::Result<()> {
             files_to_extract,
             options.recurse,
             options.progress,
+            &extract_options,
         )?,
         Some(p) => {
             let relative_path = arx::Path::from_path(&p)?;
@@ -120,6 +207,7 @@ pub fn extract(options: Options) -> anyhow::Result<()> {
                     files_to_extract,
                     options.recurse,
                     options.progress,
+                    &extract_options,
                 )?,
                 _ => return Err(anyhow::anyhow!("{} must be a directory", p.display())),
             }
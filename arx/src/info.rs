@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Context, Result};
+use arx::stats::ArchiveStats;
+use clap::{Parser, ValueHint};
+use log::info;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// Print information about an archive.
+///
+/// Report the number of entries, the size of the contents, the packs used
+/// and the largest files and directories.
+/// Exit with an error if some packs are missing.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to inspect
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    /// Number of largest files and directories to print.
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Print the information as JSON.
+    #[arg(long, default_value_t = false, action)]
+    json: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+fn sizes_to_json(sizes: &[(arx::PathBuf, u64)]) -> Value {
+    sizes
+        .iter()
+        .map(|(path, size)| json!({"path": path.as_str(), "size": size}))
+        .collect()
+}
+
+fn to_json(stats: &ArchiveStats, archive_size: u64) -> Value {
+    let entries = &stats.entries;
+    json!({
+        "archive_size": archive_size,
+        "entries": {
+            "total": entries.total(),
+            "file": entries.files,
+            "dir": entries.dirs,
+            "link": entries.links,
+            "special": entries.specials,
+            "hard_link": entries.hard_links,
+        },
+        "total_size": stats.total_size,
+        "content_size": stats.content_size,
+        "deduplicated_size": stats.deduplicated_size,
+        "packs": stats.packs.iter().map(|pack| json!({
+            "pack_id": pack.pack_id,
+            "contents": pack.contents,
+            "content_size": pack.content_size,
+            "missing": pack.missing.is_some(),
+            "uuid": pack.missing.as_ref().map(|m| m.uuid.as_str()),
+            "location": pack.missing.as_ref().map(|m| m.location.as_str()),
+        })).collect::<Vec<_>>(),
+        "largest_files": sizes_to_json(&stats.largest_files),
+        "largest_dirs": sizes_to_json(&stats.largest_dirs),
+    })
+}
+
+fn print_human(stats: &ArchiveStats, archive_size: u64) {
+    let entries = &stats.entries;
+    println!("Archive size: {archive_size}");
+    println!(
+        "Entries: {} ({} files, {} dirs, {} links, {} specials)",
+        entries.total(),
+        entries.files,
+        entries.dirs,
+        entries.links,
+        entries.specials
+    );
+    if entries.hard_links != 0 {
+        println!("Hard links: {}", entries.hard_links);
+    }
+    println!("Total size of files: {}", stats.total_size);
+    println!("Size of stored contents: {}", stats.content_size);
+    println!("Deduplicated size: {}", stats.deduplicated_size);
+    println!("Content packs: {}", stats.packs.len());
+    for pack in &stats.packs {
+        print!(
+            "  pack {}: {} contents, {} bytes",
+            pack.pack_id, pack.contents, pack.content_size
+        );
+        match &pack.missing {
+            None => println!(),
+            Some(missing) => println!(
+                ", MISSING (uuid {}, declared location {})",
+                missing.uuid, missing.location
+            ),
+        }
+    }
+    if !stats.largest_files.is_empty() {
+        println!("Largest files:");
+        for (path, size) in &stats.largest_files {
+            println!("  {size:>12} {path}");
+        }
+    }
+    if !stats.largest_dirs.is_empty() {
+        println!("Largest directories:");
+        for (path, size) in &stats.largest_dirs {
+            println!("  {size:>12} {path}");
+        }
+    }
+}
+
+pub fn info(options: Options) -> Result<()> {
+    info!("Inspect archive {:?}", options.infile);
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    let archive_size = std::fs::metadata(&options.infile)?.len();
+    let stats = arx::stats::stats(&arx, options.top)?;
+    if options.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&to_json(&stats, archive_size))?
+        );
+    } else {
+        print_human(&stats, archive_size);
+    }
+    let missing = stats.missing_packs().count();
+    if missing != 0 {
+        return Err(anyhow!("{missing} packs are missing"));
+    }
+    Ok(())
+}
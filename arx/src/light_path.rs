@@ -36,6 +36,17 @@ impl LightPath {
     pub fn println(&self, output: &mut impl Write) -> std::io::Result<()> {
         self.println2(b"", output)
     }
+
+    /// The arx path of `component` in this path.
+    pub fn join(&self, component: &[u8]) -> arx::PathBuf {
+        let mut path = arx::PathBuf::new();
+        for part in self.0.iter().map(Vec::as_slice).chain([component]) {
+            if !part.is_empty() {
+                path.push(String::from_utf8_lossy(part).as_ref());
+            }
+        }
+        path
+    }
 }
 
 impl Default for LightPath {
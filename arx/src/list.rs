@@ -26,7 +26,7 @@ impl arx::Builder for PathBuilder {
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> arx::Result<Self::Entry> {
         let path_prop = self.path_property.create(reader)?;
         let mut path = vec![];
         path_prop.resolve_to_vec(&mut path)?;
@@ -34,39 +34,64 @@ impl arx::Builder for PathBuilder {
     }
 }
 
-type LightBuilder = (PathBuilder, PathBuilder, PathBuilder);
+type LightBuilder = (PathBuilder, PathBuilder, PathBuilder, PathBuilder);
 
 struct Lister<W>
 where
     W: std::io::Write,
 {
     output: RefCell<std::io::BufWriter<W>>,
+    filter: arx::PathFilter,
+}
+
+impl<W> Lister<W>
+where
+    W: std::io::Write,
+{
+    fn print(&self, current_path: &LightPath, path: &Path) -> std::io::Result<()> {
+        if self.filter.is_empty() || self.filter.matches(&current_path.join(path)) {
+            current_path.println2(path, self.output.borrow_mut().deref_mut())?;
+        }
+        Ok(())
+    }
 }
 
 impl<W> arx::walk::Operator<LightPath, LightBuilder> for Lister<W>
 where
     W: std::io::Write,
 {
-    fn on_start(&self, _current_path: &mut LightPath) -> jbk::Result<()> {
+    fn on_start(&self, _current_path: &mut LightPath) -> arx::Result<()> {
         Ok(())
     }
-    fn on_stop(&self, _current_path: &mut LightPath) -> jbk::Result<()> {
+    fn on_stop(&self, _current_path: &mut LightPath) -> arx::Result<()> {
         Ok(())
     }
-    fn on_directory_enter(&self, current_path: &mut LightPath, path: &Path) -> jbk::Result<bool> {
+    fn on_directory_enter(&self, current_path: &mut LightPath, path: &Path) -> arx::Result<bool> {
         current_path.push(path.clone());
+        if !self.filter.is_empty() {
+            let dir_path = current_path.join(b"");
+            if !self.filter.may_match_under(&dir_path) {
+                return Ok(false);
+            }
+            if !self.filter.matches(&dir_path) {
+                return Ok(true);
+            }
+        }
         current_path.println(self.output.borrow_mut().deref_mut())?;
         Ok(true)
     }
-    fn on_directory_exit(&self, current_path: &mut LightPath, _path: &Path) -> jbk::Result<()> {
+    fn on_directory_exit(&self, current_path: &mut LightPath, _path: &Path) -> arx::Result<()> {
         current_path.pop();
         Ok(())
     }
-    fn on_file(&self, current_path: &mut LightPath, path: &Path) -> jbk::Result<()> {
-        Ok(current_path.println2(path, self.output.borrow_mut().deref_mut())?)
+    fn on_file(&self, current_path: &mut LightPath, path: &Path) -> arx::Result<()> {
+        Ok(self.print(current_path, path)?)
+    }
+    fn on_link(&self, current_path: &mut LightPath, path: &Path) -> arx::Result<()> {
+        Ok(self.print(current_path, path)?)
     }
-    fn on_link(&self, current_path: &mut LightPath, path: &Path) -> jbk::Result<()> {
-        Ok(current_path.println2(path, self.output.borrow_mut().deref_mut())?)
+    fn on_special(&self, current_path: &mut LightPath, path: &Path) -> arx::Result<()> {
+        Ok(self.print(current_path, path)?)
     }
 }
 
@@ -75,65 +100,114 @@ where
     W: std::io::Write,
 {
     output: RefCell<std::io::BufWriter<W>>,
+    version: u8,
+    filter: arx::PathFilter,
+}
+
+impl<W> StableLister<W>
+where
+    W: std::io::Write,
+{
+    fn write_prefix(&self, kind: char, entry: &impl CommonEntry) -> std::io::Result<()> {
+        let mut output = self.output.borrow_mut();
+        match self.version {
+            1 => write!(output, "{kind} {}", entry.mtime()),
+            _ => write!(output, "{kind} {:04o} {}", entry.rights(), entry.mtime()),
+        }
+    }
 }
 
 impl<W> arx::walk::Operator<arx::PathBuf, arx::FullBuilder> for StableLister<W>
 where
     W: std::io::Write,
 {
-    fn on_start(&self, _current_path: &mut arx::PathBuf) -> jbk::Result<()> {
+    fn on_start(&self, _current_path: &mut arx::PathBuf) -> arx::Result<()> {
         Ok(())
     }
-    fn on_stop(&self, _current_path: &mut arx::PathBuf) -> jbk::Result<()> {
+    fn on_stop(&self, _current_path: &mut arx::PathBuf) -> arx::Result<()> {
         Ok(())
     }
     fn on_directory_enter(
         &self,
         current_path: &mut arx::PathBuf,
         dir: &arx::Dir,
-    ) -> jbk::Result<bool> {
+    ) -> arx::Result<bool> {
         current_path.push(String::from_utf8_lossy(dir.path()).as_ref());
-        writeln!(
-            self.output.borrow_mut(),
-            "d {} {}",
-            dir.mtime(),
-            current_path
-        )?;
+        if !self.filter.may_match_under(current_path) {
+            return Ok(false);
+        }
+        if self.filter.matches(current_path) {
+            self.write_prefix('d', dir)?;
+            writeln!(self.output.borrow_mut(), " {}", current_path)?;
+        }
         Ok(true)
     }
     fn on_directory_exit(
         &self,
         current_path: &mut arx::PathBuf,
         _dir: &arx::Dir,
-    ) -> jbk::Result<()> {
+    ) -> arx::Result<()> {
         current_path.pop();
         Ok(())
     }
-    fn on_file(&self, current_path: &mut arx::PathBuf, file: &arx::FileEntry) -> jbk::Result<()> {
+    fn on_file(&self, current_path: &mut arx::PathBuf, file: &arx::FileEntry) -> arx::Result<()> {
         current_path.push(String::from_utf8_lossy(file.path()).as_ref());
+        if !self.filter.matches(current_path) {
+            current_path.pop();
+            return Ok(());
+        }
+        self.write_prefix('f', file)?;
         writeln!(
             self.output.borrow_mut(),
-            "f {} {} {}",
-            file.mtime(),
+            " {} {}",
             file.size().into_u64(),
             current_path
         )?;
         current_path.pop();
         Ok(())
     }
-    fn on_link(&self, current_path: &mut arx::PathBuf, link: &arx::Link) -> jbk::Result<()> {
+    fn on_link(&self, current_path: &mut arx::PathBuf, link: &arx::Link) -> arx::Result<()> {
         current_path.push(String::from_utf8_lossy(link.path()).as_ref());
+        if !self.filter.matches(current_path) {
+            current_path.pop();
+            return Ok(());
+        }
         let target: PathBuf = String::from_utf8_lossy(link.target()).as_ref().into();
+        self.write_prefix('l', link)?;
         writeln!(
             self.output.borrow_mut(),
-            "l {} {}->{}",
-            link.mtime(),
+            " {}->{}",
             current_path,
             target.display()
         )?;
         current_path.pop();
         Ok(())
     }
+    fn on_special(
+        &self,
+        current_path: &mut arx::PathBuf,
+        special: &arx::Special,
+    ) -> arx::Result<()> {
+        current_path.push(String::from_utf8_lossy(special.path()).as_ref());
+        if !self.filter.matches(current_path) {
+            current_path.pop();
+            return Ok(());
+        }
+        let (kind, is_device) = match special.kind() {
+            arx::SpecialKind::CharDevice => ('c', true),
+            arx::SpecialKind::BlockDevice => ('b', true),
+            arx::SpecialKind::Fifo => ('p', false),
+            arx::SpecialKind::Socket => ('s', false),
+        };
+        self.write_prefix(kind, special)?;
+        let mut output = self.output.borrow_mut();
+        if is_device {
+            write!(output, " {},{}", special.major(), special.minor())?;
+        }
+        writeln!(output, " {}", current_path)?;
+        current_path.pop();
+        Ok(())
+    }
 }
 
 /// List the content in an archive.
@@ -144,9 +218,28 @@ pub struct Options {
     infile: PathBuf,
 
     /// Use stable output (for scripting)
+    ///
+    /// Version 1 prints the type, the mtime and the path of each entry.
+    /// Version 2 also prints the rights (in octal) after the type.
+    ///
+    /// Special files are printed with type `c` (char device), `b` (block device),
+    /// `p` (fifo) or `s` (socket). Devices are followed by their `major,minor` numbers.
     #[arg(long = "stable-output", action)]
     stable_output: Option<u8>,
 
+    /// List only entries matching the glob PATTERN (`**/*.so`, `docs/**`, ...)
+    ///
+    /// May be given several times.
+    /// `*` doesn't match a `/`, use `**` to match across directories.
+    #[arg(long = "include", value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// Do not list entries matching the glob PATTERN.
+    ///
+    /// Excluding a directory excludes all its content. May be given several times.
+    #[arg(long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+
     #[arg(from_global)]
     verbose: u8,
 }
@@ -158,12 +251,15 @@ pub fn list(options: Options) -> Result<()> {
     let stdout = std::io::stdout();
     let handle = stdout.lock();
     let handle = std::io::BufWriter::new(handle);
+    let filter = arx::PathFilter::new(&options.include, &options.exclude)?;
     if let Some(version) = options.stable_output {
         match version {
-            1 => {
+            1 | 2 => {
                 let mut walker = arx::walk::Walker::new(&arx, Default::default());
                 Ok(walker.run(&StableLister {
                     output: RefCell::new(handle),
+                    version,
+                    filter,
                 })?)
             }
             _ => Err(anyhow!("Stable version {version} not supported")),
@@ -172,6 +268,7 @@ pub fn list(options: Options) -> Result<()> {
         let mut walker = arx::walk::Walker::new(&arx, Default::default());
         Ok(walker.run(&Lister {
             output: RefCell::new(handle),
+            filter,
         })?)
     }
 }
@@ -1,10 +1,15 @@
 mod create;
+mod diff;
 mod dump;
+mod export;
 mod extract;
+mod info;
 mod light_path;
 mod list;
 #[cfg(all(not(windows), feature = "fuse"))]
 mod mount;
+mod update;
+mod verify;
 
 use anyhow::Result;
 use clap::{CommandFactory, Parser};
@@ -40,6 +45,11 @@ struct Cli {
             "list",
             "dump",
             "extract",
+            "verify",
+            "update",
+            "diff",
+            "info",
+            "export",
             #[cfg(all(not(windows), feature = "fuse"))]
             "mount"
         ])
@@ -67,6 +77,21 @@ enum Commands {
     #[command(arg_required_else_help = true)]
     Extract(extract::Options),
 
+    #[command(arg_required_else_help = true)]
+    Verify(verify::Options),
+
+    #[command(arg_required_else_help = true)]
+    Update(update::Options),
+
+    #[command(arg_required_else_help = true)]
+    Diff(diff::Options),
+
+    #[command(arg_required_else_help = true, visible_alias = "stats")]
+    Info(info::Options),
+
+    #[command(arg_required_else_help = true)]
+    Export(export::Options),
+
     #[cfg(all(not(windows), feature = "fuse"))]
     #[command(arg_required_else_help = true)]
     Mount(mount::Options),
@@ -91,7 +116,7 @@ fn configure_log(verbose: u8) {
         .init();
 }
 
-fn run() -> Result<()> {
+fn run() -> Result<ExitCode> {
     let args = Cli::parse();
     configure_log(args.verbose);
 
@@ -102,33 +127,48 @@ fn run() -> Result<()> {
             "list" => list::Options::command(),
             "dump" => dump::Options::command(),
             "extract" => extract::Options::command(),
+            "verify" => verify::Options::command(),
+            "update" => update::Options::command(),
+            "diff" => diff::Options::command(),
+            "info" => info::Options::command(),
+            "export" => export::Options::command(),
             #[cfg(all(not(windows), feature = "fuse"))]
             "mount" => mount::Options::command(),
-            _ => return Ok(Cli::command().print_help()?),
+            _ => {
+                Cli::command().print_help()?;
+                return Ok(ExitCode::SUCCESS);
+            }
         };
         let man = clap_mangen::Man::new(command);
         man.render(&mut std::io::stdout())?;
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     if let Some(what) = args.generate_complete {
         let mut command = Cli::command();
         let name = command.get_name().to_string();
         clap_complete::generate(what, &mut command, name, &mut std::io::stdout());
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
-    match args.command {
+    let result = match args.command {
         None => Ok(Cli::command().print_help()?),
         Some(c) => match c {
             Commands::Create(options) => create::create(options),
             Commands::List(options) => Ok(list::list(options)?),
             Commands::Dump(options) => Ok(dump::dump(options)?),
             Commands::Extract(options) => Ok(extract::extract(options)?),
+            Commands::Verify(options) => verify::verify(options),
+            Commands::Update(options) => update::update(options),
+            // Diff has its own exit codes.
+            Commands::Diff(options) => return Ok(diff::diff(options)),
+            Commands::Info(options) => info::info(options),
+            Commands::Export(options) => export::export(options),
             #[cfg(all(not(windows), feature = "fuse"))]
             Commands::Mount(options) => Ok(mount::mount(options)?),
         },
-    }
+    };
+    result.map(|()| ExitCode::SUCCESS)
 }
 
 fn main() -> ExitCode {
@@ -144,7 +184,7 @@ fn main() -> ExitCode {
     }
 
     match run() {
-        Ok(()) => ExitCode::SUCCESS,
+        Ok(code) => code,
         Err(e) => {
             error!("Error : {e:#}");
             ExitCode::FAILURE
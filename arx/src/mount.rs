@@ -14,6 +14,8 @@ pub struct StatCounter {
     nb_opendir: u64,
     nb_readdir: u64,
     nb_releasedir: u64,
+    nb_getxattr: u64,
+    nb_listxattr: u64,
 }
 
 impl StatCounter {
@@ -28,6 +30,8 @@ impl StatCounter {
             nb_opendir: 0,
             nb_readdir: 0,
             nb_releasedir: 0,
+            nb_getxattr: 0,
+            nb_listxattr: 0,
         }
     }
 }
@@ -68,6 +72,14 @@ impl arx::Stats for StatCounter {
     fn releasedir(&mut self) {
         self.nb_releasedir += 1;
     }
+
+    fn getxattr(&mut self) {
+        self.nb_getxattr += 1;
+    }
+
+    fn listxattr(&mut self) {
+        self.nb_listxattr += 1;
+    }
 }
 
 impl Default for StatCounter {
@@ -87,6 +99,8 @@ impl std::fmt::Display for StatCounter {
         writeln!(f, "nb_opendir: {}", self.nb_opendir)?;
         writeln!(f, "nb_readdir: {}", self.nb_readdir)?;
         writeln!(f, "nb_releasedir: {}", self.nb_releasedir)?;
+        writeln!(f, "nb_getxattr: {}", self.nb_getxattr)?;
+        writeln!(f, "nb_listxattr: {}", self.nb_listxattr)?;
         Ok(())
     }
 }
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::{debug, info};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Update an archive: add, replace or remove entries.
+///
+/// A new archive is written with the entries of the existing archive and the input files.
+/// Input files replace the entries at the same path, unless they have the same size and
+/// modification time (their content is then copied from the existing archive).
+/// Directories are merged.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to update
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    /// Input files/directories to add (recursively)
+    #[arg(value_parser, value_hint=ValueHint::AnyPath)]
+    infiles: Vec<PathBuf>,
+
+    /// Write the updated archive to OUTFILE instead of replacing INFILE.
+    #[arg(short, long, value_hint=ValueHint::FilePath)]
+    outfile: Option<PathBuf>,
+
+    /// Move to BASE_DIR before adding the input files.
+    #[arg(short = 'C', required = false, value_hint=ValueHint::DirPath)]
+    base_dir: Option<PathBuf>,
+
+    /// Remove the entry at PATH (and all its content for a directory) from the archive.
+    ///
+    /// May be given several times.
+    #[arg(long, value_name = "PATH")]
+    remove: Vec<arx::PathBuf>,
+
+    /// Set compression algorithm to use
+    #[arg(short,long, value_parser=jbk::cmd_utils::compression_arg_parser, required=false, default_value = "zstd")]
+    compression: jbk::creator::Compression,
+
+    /// Compute and store a checksum of the content of added files.
+    #[arg(long, default_value_t = false, action)]
+    checksum: bool,
+
+    /// Overwrite OUTFILE if it exists.
+    #[arg(short, long, required = false, default_value_t = false, action)]
+    force: bool,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn update(options: Options) -> Result<()> {
+    let infile = std::path::absolute(&options.infile)?;
+    let (out_file, tmp_file) = match &options.outfile {
+        Some(outfile) => {
+            let outfile = std::path::absolute(outfile)?;
+            if outfile.exists() && !options.force {
+                return Err(anyhow!(
+                    "File {} already exists. Use option --force to overwrite it.",
+                    outfile.display()
+                ));
+            }
+            (outfile, None)
+        }
+        None => {
+            // Create a new file next to the archive, so it can be renamed over it.
+            let mut prefix = infile.file_name().unwrap().to_os_string();
+            prefix.push(".");
+            let tmp_file = tempfile::Builder::new()
+                .prefix(&prefix)
+                .suffix(".update")
+                .tempfile_in(infile.parent().unwrap())?;
+            (tmp_file.path().to_path_buf(), Some(tmp_file))
+        }
+    };
+    info!("Updating archive {:?} into {:?}", infile, out_file);
+
+    let arx = arx::Arx::new(&infile).with_context(|| format!("Opening {:?}", infile))?;
+    if let Some(base_dir) = &options.base_dir {
+        std::env::set_current_dir(base_dir)?;
+    };
+
+    let mut creator = arx::create::SimpleCreator::new(
+        &out_file,
+        jbk::creator::ConcatMode::OneFile,
+        Arc::new(()),
+        Rc::new(()),
+        options.compression,
+    )?;
+
+    let mut fs_adder = arx::create::FsAdder::new(&mut creator, arx::PathBuf::new());
+    fs_adder.set_checksum(options.checksum);
+    fs_adder.set_reference(&arx);
+    for infile in &options.infiles {
+        if infile.is_absolute() {
+            return Err(anyhow!(
+                "Input file ({}) must be relative.",
+                infile.display()
+            ));
+        }
+        debug!("Adding file {infile:?}");
+        fs_adder.add_from_path(infile, true)?;
+    }
+    arx::create::carry_over(&arx, &mut creator, &options.remove)?;
+    creator.finalize(&out_file)?;
+    drop(arx);
+
+    if let Some(tmp_file) = tmp_file {
+        std::fs::set_permissions(tmp_file.path(), std::fs::metadata(&infile)?.permissions())?;
+        tmp_file.persist(&infile)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, ValueHint};
+use log::info;
+use std::path::PathBuf;
+
+/// Verify the integrity of an archive.
+///
+/// Read the content of all files and compare it with the checksum stored
+/// at creation (if any). Missing packs and unreadable contents are reported.
+#[derive(Parser, Debug)]
+pub struct Options {
+    /// Archive to verify
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    infile: PathBuf,
+
+    #[arg(from_global)]
+    verbose: u8,
+}
+
+pub fn verify(options: Options) -> Result<()> {
+    info!("Verify archive {:?}", options.infile);
+    let arx =
+        arx::Arx::new(&options.infile).with_context(|| format!("Opening {:?}", options.infile))?;
+    let report = arx::verify::verify(&arx)?;
+    for issue in &report.issues {
+        println!("{issue}");
+    }
+    if report.without_checksum != 0 {
+        info!(
+            "{} files have no checksum, only their readability has been checked",
+            report.without_checksum
+        );
+    }
+    if report.is_ok() {
+        println!("{} files checked, no error found", report.checked_files);
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} errors found in {} files checked",
+            report.issues.len(),
+            report.checked_files
+        ))
+    }
+}
@@ -242,6 +242,7 @@ fn test_create_and_extract() {
         Default::default(),
         true,
         false,
+        &Default::default(),
     )
     .unwrap();
     let output = cmd!("diff", "-r", source_mount_point, extract_dir.path());
@@ -284,6 +285,7 @@ fn test_create_and_extract_filter() {
         ["OrcBlIw".into()].into(),
         true,
         true,
+        &Default::default(),
     )
     .unwrap();
 
@@ -398,3 +400,1450 @@ fn test_create_and_extract_subfile() {
     );
     assert!(!output.status.success());
 }
+
+#[cfg(unix)]
+#[test]
+fn test_create_keep_special_rights() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let sub_dir = source_dir.path().join("sub_dir");
+    std::fs::create_dir(&sub_dir).unwrap();
+    let file = sub_dir.join("file.txt");
+    std::fs::write(&file, b"Some content").unwrap();
+    std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o4750)).unwrap();
+    std::fs::set_permissions(&sub_dir, std::fs::Permissions::from_mode(0o1777)).unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub_dir"
+    );
+    assert!(
+        output.status.success(),
+        "arx create failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = cmd!("arx", "list", "--stable-output", "2", &arx_file);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        output.status.success(),
+        "arx list failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let mut lines = stdout.lines();
+    let dir_line = lines.next().unwrap();
+    assert!(
+        dir_line.starts_with("d 1777 "),
+        "Unexpected line: {dir_line}"
+    );
+    let file_line = lines.next().unwrap();
+    assert!(
+        file_line.starts_with("f 4750 "),
+        "Unexpected line: {file_line}"
+    );
+    assert!(
+        file_line.ends_with(" 12 sub_dir/file.txt"),
+        "Unexpected line: {file_line}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_and_extract_preserve_metadata() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let sub_dir = source_dir.path().join("sub_dir");
+    std::fs::create_dir(&sub_dir).unwrap();
+    let file_path = sub_dir.join("file.txt");
+    let file = std::fs::File::create(&file_path).unwrap();
+    file.set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000_000))
+        .unwrap();
+    drop(file);
+    std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o751)).unwrap();
+    std::fs::File::open(&sub_dir)
+        .unwrap()
+        .set_modified(UNIX_EPOCH + Duration::from_secs(1_200_000_000))
+        .unwrap();
+    std::fs::set_permissions(&sub_dir, std::fs::Permissions::from_mode(0o750)).unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub_dir"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &arx::ExtractOptions {
+            preserve_mode: true,
+            preserve_mtime: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let metadata = std::fs::metadata(extract_dir.path().join("sub_dir")).unwrap();
+    assert_eq!(metadata.mode() & 0o7777, 0o750);
+    assert_eq!(metadata.mtime(), 1_200_000_000);
+    let metadata = std::fs::metadata(extract_dir.path().join("sub_dir/file.txt")).unwrap();
+    assert_eq!(metadata.mode() & 0o7777, 0o751);
+    assert_eq!(metadata.mtime(), 1_000_000_000);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_and_extract_xattrs() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let file_path = source_dir.path().join("file.txt");
+    std::fs::write(&file_path, "Some content").unwrap();
+    if let Err(e) = xattr::set(&file_path, "user.comment", b"A comment") {
+        // Filesystem of the target dir doesn't support user xattrs.
+        println!("Skipping test, cannot set xattr: {e}");
+        return;
+    }
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "file.txt"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let output = cmd!(
+        "arx",
+        "extract",
+        &arx_file,
+        "-C",
+        extract_dir.path(),
+        "--preserve-xattrs"
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        xattr::get(extract_dir.path().join("file.txt"), "user.comment").unwrap(),
+        Some(b"A comment".to_vec())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_and_extract_hard_links() {
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let sub_dir = source_dir.path().join("sub_dir");
+    std::fs::create_dir(&sub_dir).unwrap();
+    std::fs::write(sub_dir.join("file.txt"), "Some content").unwrap();
+    std::fs::hard_link(sub_dir.join("file.txt"), sub_dir.join("link.txt")).unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub_dir"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &Default::default(),
+    )
+    .unwrap();
+
+    let file_metadata = std::fs::metadata(extract_dir.path().join("sub_dir/file.txt")).unwrap();
+    let link_metadata = std::fs::metadata(extract_dir.path().join("sub_dir/link.txt")).unwrap();
+    assert_eq!(file_metadata.nlink(), 2);
+    assert_eq!(file_metadata.ino(), link_metadata.ino());
+    assert_eq!(
+        std::fs::read_to_string(extract_dir.path().join("sub_dir/link.txt")).unwrap(),
+        "Some content"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_and_extract_fifo() {
+    use std::os::unix::fs::FileTypeExt;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let sub_dir = source_dir.path().join("sub_dir");
+    std::fs::create_dir(&sub_dir).unwrap();
+    let output = cmd!("mkfifo", sub_dir.join("fifo"));
+    assert!(output.status.success());
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub_dir"
+    );
+    assert!(output.status.success());
+
+    let output = cmd!("arx", "list", "--stable-output", "1", &arx_file);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    let mut lines = stdout.lines();
+    assert!(lines.next().unwrap().starts_with("d "));
+    let fifo_line = lines.next().unwrap();
+    assert!(fifo_line.starts_with("p "));
+    assert!(fifo_line.ends_with(" sub_dir/fifo"));
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &Default::default(),
+    )
+    .unwrap();
+    let metadata = std::fs::symlink_metadata(extract_dir.path().join("sub_dir/fifo")).unwrap();
+    assert!(metadata.file_type().is_fifo());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_and_extract_nsec_mtime() {
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let file_path = source_dir.path().join("file.txt");
+    let file = std::fs::File::create(&file_path).unwrap();
+    file.set_times(
+        std::fs::FileTimes::new()
+            .set_accessed(UNIX_EPOCH + Duration::new(1_100_000_000, 987_654_321))
+            .set_modified(UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789)),
+    )
+    .unwrap();
+    drop(file);
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "file.txt"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &arx::ExtractOptions {
+            preserve_mtime: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let metadata = std::fs::metadata(extract_dir.path().join("file.txt")).unwrap();
+    assert_eq!(metadata.mtime(), 1_000_000_000);
+    assert_eq!(metadata.mtime_nsec(), 123_456_789);
+    assert_eq!(metadata.atime(), 1_100_000_000);
+    assert_eq!(metadata.atime_nsec(), 987_654_321);
+}
+
+#[test]
+fn test_create_with_checksum_and_verify() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::write(source_dir.path().join("file.txt"), b"Some content").unwrap();
+    // Big enough to not be read in memory before being added.
+    let big_content: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    std::fs::write(source_dir.path().join("big_file"), &big_content).unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "--checksum",
+        "-C",
+        source_dir.path(),
+        "file.txt",
+        "big_file"
+    );
+    assert!(output.status.success());
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    let report = arx::verify::verify(&arx).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.checked_files, 2);
+    assert_eq!(report.without_checksum, 0);
+
+    let output = cmd!("arx", "verify", &arx_file);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_get_entry_errors() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::write(source_dir.path().join("file.txt"), b"Some content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "file.txt"
+    );
+    assert!(output.status.success());
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    assert!(matches!(
+        arx.get_entry::<arx::FullBuilder>(arx::Path::new("missing.txt")),
+        Err(arx::Error::NotFound(p)) if p == "missing.txt"
+    ));
+    assert!(matches!(
+        arx.get_entry::<arx::FullBuilder>(arx::Path::new("file.txt/foo")),
+        Err(arx::Error::NotADirectory(p)) if p == "file.txt"
+    ));
+}
+
+#[test]
+fn test_extract_collect_errors() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::write(source_dir.path().join("a.txt"), b"Content of a").unwrap();
+    std::fs::write(source_dir.path().join("b.txt"), b"Content of b").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "a.txt",
+        "b.txt"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    std::fs::write(extract_dir.path().join("a.txt"), b"Existing").unwrap();
+    let result = arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &arx::ExtractOptions {
+            continue_on_error: true,
+            ..Default::default()
+        },
+    );
+    match result {
+        Err(arx::Error::Extract(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].0, extract_dir.path().join("a.txt"));
+        }
+        _ => panic!("Extraction should fail on a.txt"),
+    }
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("a.txt")).unwrap(),
+        b"Existing"
+    );
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("b.txt")).unwrap(),
+        b"Content of b"
+    );
+}
+
+#[test]
+fn test_extract_overwrite_policies() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::write(source_dir.path().join("file.txt"), b"New content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "file.txt"
+    );
+    assert!(output.status.success());
+
+    let extract = |policy| {
+        let extract_dir =
+            tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+        std::fs::write(extract_dir.path().join("file.txt"), b"Old content").unwrap();
+        let result = arx::extract(
+            &arx_file,
+            extract_dir.path(),
+            Default::default(),
+            true,
+            false,
+            &arx::ExtractOptions {
+                overwrite: policy,
+                ..Default::default()
+            },
+        );
+        (extract_dir, result)
+    };
+
+    let (_dir, result) = extract(arx::OverwritePolicy::Error);
+    assert!(result.is_err());
+
+    let (dir, result) = extract(arx::OverwritePolicy::Skip);
+    result.unwrap();
+    assert_eq!(
+        std::fs::read(dir.path().join("file.txt")).unwrap(),
+        b"Old content"
+    );
+
+    let (dir, result) = extract(arx::OverwritePolicy::Overwrite);
+    result.unwrap();
+    assert_eq!(
+        std::fs::read(dir.path().join("file.txt")).unwrap(),
+        b"New content"
+    );
+
+    let (dir, result) = extract(arx::OverwritePolicy::Rename);
+    result.unwrap();
+    assert_eq!(
+        std::fs::read(dir.path().join("file.txt")).unwrap(),
+        b"New content"
+    );
+    assert_eq!(
+        std::fs::read(dir.path().join("file.txt.~1~")).unwrap(),
+        b"Old content"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_keep_newer() {
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    let set_mtime = |path: &Path, secs, nsecs| {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::new(secs, nsecs))
+            .unwrap();
+    };
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::write(source_dir.path().join("file.txt"), b"New content").unwrap();
+    std::fs::hard_link(
+        source_dir.path().join("file.txt"),
+        source_dir.path().join("link.txt"),
+    )
+    .unwrap();
+    std::fs::write(source_dir.path().join("other.txt"), b"New content").unwrap();
+    set_mtime(&source_dir.path().join("file.txt"), 1000, 500);
+    set_mtime(&source_dir.path().join("other.txt"), 1000, 500);
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "file.txt",
+        "link.txt",
+        "other.txt"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    // Newer than the entry.
+    std::fs::write(extract_dir.path().join("file.txt"), b"Old content").unwrap();
+    set_mtime(&extract_dir.path().join("file.txt"), 2000, 0);
+    // Older than the entry, in the same second.
+    std::fs::write(extract_dir.path().join("other.txt"), b"Old content").unwrap();
+    set_mtime(&extract_dir.path().join("other.txt"), 1000, 100);
+
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &arx::ExtractOptions {
+            overwrite: arx::OverwritePolicy::KeepNewer,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("file.txt")).unwrap(),
+        b"Old content"
+    );
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("other.txt")).unwrap(),
+        b"New content"
+    );
+    // The hard link must not be linked to the kept (unrelated) file.
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("link.txt")).unwrap(),
+        b"New content"
+    );
+    let file_metadata = std::fs::metadata(extract_dir.path().join("file.txt")).unwrap();
+    let link_metadata = std::fs::metadata(extract_dir.path().join("link.txt")).unwrap();
+    assert_ne!(file_metadata.ino(), link_metadata.ino());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_extract_dont_write_through_symlink() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir(source_dir.path().join("sub")).unwrap();
+    std::fs::write(source_dir.path().join("sub/file.txt"), b"Some content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub"
+    );
+    assert!(output.status.success());
+
+    // `sub` in the output directory points outside of it.
+    let outside_dir =
+        tempfile::TempDir::with_prefix_in("outside_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    std::os::unix::fs::symlink(outside_dir.path(), extract_dir.path().join("sub")).unwrap();
+
+    let result = arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &Default::default(),
+    );
+    assert!(result.is_err());
+    assert!(!outside_dir.path().join("file.txt").exists());
+}
+
+#[test]
+fn test_extract_strip_and_transform() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(source_dir.path().join("sub/dir")).unwrap();
+    std::fs::write(source_dir.path().join("sub/dir/file.txt"), b"Some content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &arx::ExtractOptions {
+            strip_components: 1,
+            transforms: vec![
+                "s/file/renamed/".parse().unwrap(),
+                "dir=other".parse().unwrap(),
+            ],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(!extract_dir.path().join("sub").exists());
+    assert_eq!(
+        std::fs::read(extract_dir.path().join("other/renamed.txt")).unwrap(),
+        b"Some content"
+    );
+}
+
+#[test]
+fn test_extract_and_list_with_patterns() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(source_dir.path().join("sub/lib")).unwrap();
+    std::fs::create_dir_all(source_dir.path().join("sub/docs/api")).unwrap();
+    std::fs::write(source_dir.path().join("sub/lib/libfoo.so"), b"lib").unwrap();
+    std::fs::write(source_dir.path().join("sub/lib/foo.h"), b"header").unwrap();
+    std::fs::write(source_dir.path().join("sub/docs/api/index.html"), b"doc").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "sub"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &arx::ExtractOptions {
+            filter: arx::PathFilter::new(&["**/*.so", "sub/docs/**"], &["sub/docs/api"]).unwrap(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(extract_dir.path().join("sub/lib/libfoo.so").is_file());
+    assert!(!extract_dir.path().join("sub/lib/foo.h").exists());
+    assert!(extract_dir.path().join("sub/docs").is_dir());
+    assert!(!extract_dir.path().join("sub/docs/api").exists());
+
+    let output = cmd!(
+        "arx",
+        "list",
+        &arx_file,
+        "--include",
+        "sub/lib/**",
+        "--exclude",
+        "**/*.h"
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "sub/lib\nsub/lib/libfoo.so\n"
+    );
+
+    // `*` doesn't match across directories.
+    let filter = arx::PathFilter::new::<&str>(&["sub/*.so"], &[]).unwrap();
+    assert!(!filter.matches(arx::Path::new("sub/lib/libfoo.so")));
+    let filter = arx::PathFilter::new::<&str>(&["sub/*/*.so"], &[]).unwrap();
+    assert!(filter.matches(arx::Path::new("sub/lib/libfoo.so")));
+}
+
+#[test]
+fn test_create_with_excludes() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let root = source_dir.path().join("project");
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("target/debug")).unwrap();
+    std::fs::create_dir_all(root.join("src/gen")).unwrap();
+    std::fs::write(root.join(".git/HEAD"), b"ref").unwrap();
+    std::fs::write(root.join("target/debug/bin"), b"bin").unwrap();
+    std::fs::write(root.join("src/main.rs"), b"fn main() {}").unwrap();
+    std::fs::write(root.join("src/main.o"), b"obj").unwrap();
+    std::fs::write(root.join("src/.gitignore"), b"gen/\n").unwrap();
+    std::fs::write(root.join("src/gen/out.rs"), b"generated").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "--exclude",
+        "target/",
+        "--exclude",
+        "*.o",
+        "--exclude-vcs",
+        "--respect-gitignore",
+        "project"
+    );
+    assert!(output.status.success());
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    type DummyBuilder = ((), (), ());
+    for path in ["project/src", "project/src/main.rs"] {
+        assert!(arx.get_entry::<DummyBuilder>(arx::Path::new(path)).is_ok());
+    }
+    for path in [
+        "project/.git",
+        "project/src/.gitignore",
+        "project/target",
+        "project/src/main.o",
+        "project/src/gen",
+    ] {
+        assert!(matches!(
+            arx.get_entry::<DummyBuilder>(arx::Path::new(path)),
+            Err(arx::Error::NotFound(_))
+        ));
+    }
+}
+
+#[test]
+fn test_create_reproducible() {
+    use arx::CommonEntry;
+    use std::path::Path;
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let mut listings = vec![];
+    for (idx, names) in [["a.txt", "b.txt"], ["b.txt", "a.txt"]].iter().enumerate() {
+        // Create the files in different orders.
+        let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+            .expect("Creating tempdir should work");
+        std::fs::create_dir(source_dir.path().join("dir")).unwrap();
+        for name in names {
+            std::fs::write(source_dir.path().join("dir").join(name), name.as_bytes()).unwrap();
+        }
+
+        let arx_file = arx_tmp_dir.path().join(format!("test{idx}.arx"));
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_arx"))
+            .env("NO_COLOR", "1")
+            .env("SOURCE_DATE_EPOCH", "1000000")
+            .arg("create")
+            .arg("--outfile")
+            .arg(&arx_file)
+            .arg("-C")
+            .arg(source_dir.path())
+            .args(["--reproducible", "--mode-mask", "022", "dir"])
+            .output()
+            .expect("Launching arx command should work.");
+        assert!(output.status.success());
+
+        let arx = arx::Arx::new(&arx_file).unwrap();
+        match arx
+            .get_entry::<arx::FullBuilder>(arx::Path::new("dir/a.txt"))
+            .unwrap()
+        {
+            arx::Entry::File(file) => {
+                assert_eq!(file.mtime(), 1000000);
+                assert_eq!(file.owner(), 0);
+                assert_eq!(file.rights() & 0o022, 0);
+            }
+            _ => panic!("dir/a.txt must be a file"),
+        }
+
+        let output = cmd!("arx", "list", &arx_file, "--stable-output", "2");
+        assert!(output.status.success());
+        listings.push(output.stdout);
+    }
+    assert_eq!(listings[0], listings[1]);
+}
+
+#[test]
+fn test_create_source_date_epoch() {
+    use arx::CommonEntry;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::write(source_dir.path().join("a.txt"), b"a content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    // No --reproducible, SOURCE_DATE_EPOCH alone must give deterministic times.
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_arx"))
+        .env("NO_COLOR", "1")
+        .env("SOURCE_DATE_EPOCH", "1000000")
+        .arg("create")
+        .arg("--outfile")
+        .arg(&arx_file)
+        .arg("-C")
+        .arg(source_dir.path())
+        .arg("a.txt")
+        .output()
+        .expect("Launching arx command should work.");
+    assert!(output.status.success());
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    match arx
+        .get_entry::<arx::FullBuilder>(arx::Path::new("a.txt"))
+        .unwrap()
+    {
+        arx::Entry::File(file) => {
+            assert_eq!(file.mtime(), 1000000);
+            assert_eq!(file.atime_ns(), None);
+            assert_eq!(file.ctime_ns(), None);
+            assert_eq!(file.btime_ns(), None);
+        }
+        _ => panic!("a.txt must be a file"),
+    }
+}
+
+#[test]
+#[ignore = "Jubako generates random pack UUIDs and doesn't allow to set them"]
+fn test_create_reproducible_bytes() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir(source_dir.path().join("dir")).unwrap();
+    std::fs::write(source_dir.path().join("dir/a.txt"), b"a content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let mut archives = vec![];
+    for idx in 0..2 {
+        let arx_file = arx_tmp_dir.path().join(format!("test{idx}.arx"));
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_arx"))
+            .env("NO_COLOR", "1")
+            .env("SOURCE_DATE_EPOCH", "1000000")
+            .arg("create")
+            .arg("--outfile")
+            .arg(&arx_file)
+            .arg("-C")
+            .arg(source_dir.path())
+            .args(["--reproducible", "dir"])
+            .output()
+            .expect("Launching arx command should work.");
+        assert!(output.status.success());
+        archives.push(std::fs::read(&arx_file).unwrap());
+    }
+    assert!(archives[0] == archives[1], "Archives must be identical");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_implicit_dirs() {
+    use arx::CommonEntry;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir(source_dir.path().join("dir")).unwrap();
+    std::fs::write(source_dir.path().join("dir/file.txt"), b"content").unwrap();
+    std::fs::set_permissions(
+        source_dir.path().join("dir"),
+        std::fs::Permissions::from_mode(0o700),
+    )
+    .unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let file_list = arx_tmp_dir.path().join("file_list.txt");
+    std::fs::write(&file_list, b"dir/file.txt\n").unwrap();
+
+    for (policy, expected_rights) in [("fixed", 0o755), ("filesystem", 0o700)] {
+        let arx_file = arx_tmp_dir.path().join(format!("{policy}.arx"));
+        let output = cmd!(
+            "arx",
+            "create",
+            "--outfile",
+            &arx_file,
+            "-C",
+            source_dir.path(),
+            "-L",
+            &file_list,
+            "--implicit-dirs",
+            policy
+        );
+        assert!(output.status.success());
+
+        let arx = arx::Arx::new(&arx_file).unwrap();
+        match arx
+            .get_entry::<arx::FullBuilder>(arx::Path::new("dir"))
+            .unwrap()
+        {
+            arx::Entry::Dir(_, dir) => assert_eq!(dir.rights(), expected_rights),
+            _ => panic!("dir must be a directory"),
+        }
+    }
+}
+
+#[test]
+fn test_create_implicit_dir_fixed_values() {
+    use arx::CommonEntry;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir(source_dir.path().join("dir")).unwrap();
+    std::fs::write(source_dir.path().join("dir/file.txt"), b"content").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let file_list = arx_tmp_dir.path().join("file_list.txt");
+    std::fs::write(&file_list, b"dir/file.txt\n").unwrap();
+
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "-L",
+        &file_list,
+        "--implicit-dir-owner",
+        "12",
+        "--implicit-dir-group",
+        "34",
+        "--implicit-dir-mode",
+        "750",
+        "--implicit-dir-mtime",
+        "1000000000"
+    );
+    assert!(output.status.success());
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    match arx
+        .get_entry::<arx::FullBuilder>(arx::Path::new("dir"))
+        .unwrap()
+    {
+        arx::Entry::Dir(_, dir) => {
+            assert_eq!(dir.owner(), 12);
+            assert_eq!(dir.group(), 34);
+            assert_eq!(dir.rights(), 0o750);
+            assert_eq!(dir.mtime(), 1_000_000_000);
+        }
+        _ => panic!("dir must be a directory"),
+    }
+
+    // Fixed values make no sense with other policies.
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        arx_tmp_dir.path().join("inherit.arx"),
+        "-C",
+        source_dir.path(),
+        "-L",
+        &file_list,
+        "--implicit-dirs",
+        "inherit",
+        "--implicit-dir-mode",
+        "750"
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_create_many_files() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    // More files than entries prepared at once.
+    for i in 0..500 {
+        let dir = source_dir.path().join(format!("root/dir{}", i % 7));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("file{i}.txt")), format!("content {i}")).unwrap();
+    }
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "--checksum",
+        "root"
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &Default::default(),
+    )
+    .unwrap();
+    for i in 0..500 {
+        let path = extract_dir
+            .path()
+            .join(format!("root/dir{}/file{i}.txt", i % 7));
+        assert_eq!(
+            std::fs::read(path).unwrap(),
+            format!("content {i}").as_bytes()
+        );
+    }
+
+    let output = cmd!("arx", "verify", &arx_file);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_update() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(source_dir.path().join("data/old")).unwrap();
+    std::fs::write(source_dir.path().join("data/kept.txt"), b"kept").unwrap();
+    std::fs::write(source_dir.path().join("data/replaced.txt"), b"old content").unwrap();
+    std::fs::write(source_dir.path().join("data/old/removed.txt"), b"removed").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+
+    // Only the updated files are given.
+    let update_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(update_dir.path().join("data/new")).unwrap();
+    std::fs::write(update_dir.path().join("data/replaced.txt"), b"new content").unwrap();
+    std::fs::write(update_dir.path().join("data/new/added.txt"), b"added").unwrap();
+    // An unrelated file must not be overwritten.
+    let other_file = arx_tmp_dir.path().join("test.arx.update");
+    std::fs::write(&other_file, b"other").unwrap();
+    let output = cmd!(
+        "arx",
+        "update",
+        &arx_file,
+        "-C",
+        update_dir.path(),
+        "--remove",
+        "data/old",
+        "data"
+    );
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&other_file).unwrap(), b"other");
+    // The temporary archive has been renamed over the updated one.
+    assert_eq!(std::fs::read_dir(arx_tmp_dir.path()).unwrap().count(), 2);
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    arx::extract(
+        &arx_file,
+        extract_dir.path(),
+        Default::default(),
+        true,
+        false,
+        &Default::default(),
+    )
+    .unwrap();
+    let extracted = extract_dir.path().join("data");
+    assert_eq!(std::fs::read(extracted.join("kept.txt")).unwrap(), b"kept");
+    assert_eq!(
+        std::fs::read(extracted.join("replaced.txt")).unwrap(),
+        b"new content"
+    );
+    assert_eq!(
+        std::fs::read(extracted.join("new/added.txt")).unwrap(),
+        b"added"
+    );
+    assert!(!extracted.join("old").exists());
+}
+
+#[test]
+fn test_diff() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(source_dir.path().join("data")).unwrap();
+    std::fs::write(source_dir.path().join("data/same.txt"), b"same").unwrap();
+    std::fs::write(source_dir.path().join("data/changed.txt"), b"content 1").unwrap();
+    std::fs::write(source_dir.path().join("data/removed.txt"), b"removed").unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let old_arx = arx_tmp_dir.path().join("old.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &old_arx,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+
+    let output = cmd!("arx", "diff", &old_arx, source_dir.path());
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    std::fs::write(source_dir.path().join("data/changed.txt"), b"content 2").unwrap();
+    std::fs::remove_file(source_dir.path().join("data/removed.txt")).unwrap();
+    std::fs::write(source_dir.path().join("data/added.txt"), b"added").unwrap();
+
+    let output = cmd!("arx", "diff", "--ignore-mtime", &old_arx, source_dir.path());
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "+ data/added.txt",
+            "M data/changed.txt: content",
+            "- data/removed.txt"
+        ]
+    );
+
+    let new_arx = arx_tmp_dir.path().join("new.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &new_arx,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+    let output = cmd!(
+        "arx",
+        "diff",
+        "--json",
+        "--ignore-metadata",
+        &old_arx,
+        &new_arx
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""status": "added""#));
+    assert!(stdout.contains(r#""path": "data/added.txt""#));
+    assert!(stdout.contains(r#""field": "content""#));
+    assert!(stdout.contains(r#""status": "removed""#));
+    assert!(!stdout.contains("same.txt"));
+
+    let output = cmd!(
+        "arx",
+        "diff",
+        &old_arx,
+        arx_tmp_dir.path().join("missing.arx")
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_diff_non_utf8_link() {
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir(source_dir.path().join("data")).unwrap();
+    std::os::unix::fs::symlink(
+        std::ffi::OsStr::from_bytes(b"target_\xff"),
+        source_dir.path().join("data/link"),
+    )
+    .unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+
+    let output = cmd!("arx", "diff", &arx_file, source_dir.path());
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_info() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(source_dir.path().join("data/sub")).unwrap();
+    std::fs::write(source_dir.path().join("data/small.txt"), b"small").unwrap();
+    std::fs::write(source_dir.path().join("data/sub/big.txt"), vec![b'a'; 1000]).unwrap();
+    std::fs::write(
+        source_dir.path().join("data/sub/copy.txt"),
+        vec![b'a'; 1000],
+    )
+    .unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    let stats = arx::stats::stats(&arx, 2).unwrap();
+    assert_eq!(stats.entries.files, 3);
+    assert_eq!(stats.entries.dirs, 2);
+    assert_eq!(stats.total_size, 2005);
+    assert_eq!(stats.content_size + stats.deduplicated_size, 2005);
+    assert_eq!(stats.missing_packs().count(), 0);
+    assert_eq!(stats.largest_files.len(), 2);
+    assert_eq!(stats.largest_files[0].1, 1000);
+    assert_eq!(stats.largest_dirs[0], (arx::PathBuf::from("data"), 2005));
+
+    let output = cmd!("arx", "info", "--json", &arx_file);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""total_size": 2005"#));
+    assert!(stdout.contains(r#""path": "data/sub""#));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_tar() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let long_dir = format!("data/{}", "long_directory_name_".repeat(8));
+    std::fs::create_dir_all(source_dir.path().join(&long_dir)).unwrap();
+    std::fs::write(source_dir.path().join("data/file.txt"), b"content").unwrap();
+    std::fs::write(
+        source_dir.path().join(&long_dir).join("file.txt"),
+        b"deep content",
+    )
+    .unwrap();
+    std::os::unix::fs::symlink("file.txt", source_dir.path().join("data/link")).unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+
+    let tar_file = arx_tmp_dir.path().join("test.tar.gz");
+    let output = cmd!("arx", "export", &arx_file, "--outfile", &tar_file);
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let output = cmd!("tar", "-xzf", &tar_file, "-C", extract_dir.path());
+    assert!(output.status.success());
+    let output = cmd!(
+        "diff",
+        "-r",
+        source_dir.path().join("data"),
+        extract_dir.path().join("data")
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_link(extract_dir.path().join("data/link")).unwrap(),
+        Path::new("file.txt")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_zip() {
+    use std::path::Path;
+
+    let source_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    std::fs::create_dir_all(source_dir.path().join("data/sub")).unwrap();
+    std::fs::write(source_dir.path().join("data/file.txt"), b"content").unwrap();
+    std::fs::write(source_dir.path().join("data/sub/empty"), b"").unwrap();
+    std::fs::write(
+        source_dir.path().join("data/sub/other.txt"),
+        b"other content",
+    )
+    .unwrap();
+
+    let arx_tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let arx_file = arx_tmp_dir.path().join("test.arx");
+    let output = cmd!(
+        "arx",
+        "create",
+        "--outfile",
+        &arx_file,
+        "-C",
+        source_dir.path(),
+        "data"
+    );
+    assert!(output.status.success());
+
+    let zip_file = arx_tmp_dir.path().join("test.zip");
+    let output = cmd!(
+        "arx",
+        "export",
+        &arx_file,
+        "--root-dir",
+        "data",
+        "--outfile",
+        &zip_file
+    );
+    assert!(output.status.success());
+
+    let extract_dir =
+        tempfile::TempDir::with_prefix_in("extract_", env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let output = cmd!("unzip", "-q", &zip_file, "-d", extract_dir.path());
+    assert!(output.status.success());
+    let output = cmd!(
+        "diff",
+        "-r",
+        source_dir.path().join("data"),
+        extract_dir.path()
+    );
+    assert!(output.status.success());
+}
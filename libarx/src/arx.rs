@@ -19,15 +19,15 @@ impl std::ops::Deref for Arx {
 fn create_properties(
     container: &jbk::reader::Container,
     index: &jbk::reader::Index,
-) -> jbk::Result<AllProperties> {
-    AllProperties::new(
+) -> crate::Result<AllProperties> {
+    Ok(AllProperties::new(
         index.get_store(container.get_entry_storage())?,
         container.get_value_storage(),
-    )
+    )?)
 }
 
 impl Arx {
-    pub fn new<P: AsRef<Path>>(file: P) -> jbk::Result<Self> {
+    pub fn new<P: AsRef<Path>>(file: P) -> crate::Result<Self> {
         let container = jbk::reader::Container::new(&file)?;
         let root_index = container
             .get_directory_pack()
@@ -40,19 +40,21 @@ impl Arx {
         })
     }
 
-    pub fn create_properties(&self, index: &jbk::reader::Index) -> jbk::Result<AllProperties> {
+    pub fn create_properties(&self, index: &jbk::reader::Index) -> crate::Result<AllProperties> {
         create_properties(&self.container, index)
     }
 
-    pub fn get_entry<B>(&self, path: &crate::Path) -> jbk::Result<Entry<B::Entry>>
+    pub fn get_entry<B>(&self, path: &crate::Path) -> crate::Result<Entry<B::Entry>>
     where
         B: FullBuilderTrait,
     {
         let comparator = Comparator::new(&self.properties);
         let builder = RealBuilder::<B>::new(&self.properties);
         let mut current_range: jbk::EntryRange = (&self.root_index).into();
+        let mut current_path = crate::PathBuf::new();
         let mut components = path.iter().peekable();
         while let Some(component) = components.next() {
+            current_path.push(component);
             // Search for the current component.
             // All children of a parent are stored concatened.
             // So if parent_id is different than current_parent,
@@ -60,7 +62,7 @@ impl Arx {
             let comparator = comparator.compare_with(component.as_bytes());
             let found = current_range.find(&comparator)?;
             match found {
-                None => return Err("Cannot found entry".to_string().into()),
+                None => return Err(crate::Error::NotFound(path.to_owned())),
                 Some(idx) => {
                     let entry = current_range.get_entry(&builder, idx)?;
                     if components.peek().is_none() {
@@ -69,7 +71,7 @@ impl Arx {
                     } else if let Entry::Dir(range, _) = entry {
                         current_range = range;
                     } else {
-                        return Err("Cannot found entry".to_string().into());
+                        return Err(crate::Error::NotADirectory(current_path));
                     }
                 }
             }
@@ -77,11 +79,11 @@ impl Arx {
         unreachable!();
     }
 
-    pub fn get_entry_at_idx<B>(&self, idx: EntryIdx) -> jbk::Result<Entry<B::Entry>>
+    pub fn get_entry_at_idx<B>(&self, idx: EntryIdx) -> crate::Result<Entry<B::Entry>>
     where
         B: FullBuilderTrait,
     {
         let builder = RealBuilder::<B>::new(&self.properties);
-        builder.create_entry(idx)
+        Ok(builder.create_entry(idx)?)
     }
 }
@@ -1,5 +1,5 @@
 use super::Arx;
-use crate::common::{AllProperties, Comparator, EntryType, ReadEntry};
+use crate::common::{AllProperties, Comparator, EntryType, ReadEntry, SpecialKind};
 use fxhash::FxBuildHasher;
 use jbk::reader::builder::PropertyBuilderTrait;
 use jbk::reader::{MayMissPack, Range};
@@ -29,6 +29,8 @@ pub trait Stats {
     fn opendir(&mut self) {}
     fn readdir(&mut self) {}
     fn releasedir(&mut self) {}
+    fn getxattr(&mut self) {}
+    fn listxattr(&mut self) {}
 }
 
 impl Stats for () {}
@@ -182,15 +184,40 @@ impl jbk::reader::builder::BuilderTrait for LightDirBuilder {
     }
 }
 
+/// Get the fuser file type of a entry, reading the kind of special entries.
+fn file_type(
+    entry_type: EntryType,
+    special_kind_property: &Option<jbk::reader::builder::IntProperty>,
+    reader: &jbk::reader::ByteSlice,
+) -> jbk::Result<fuser::FileType> {
+    Ok(match entry_type {
+        EntryType::File => fuser::FileType::RegularFile,
+        EntryType::Dir => fuser::FileType::Directory,
+        EntryType::Link => fuser::FileType::Symlink,
+        EntryType::Special => {
+            // Special entries exist only if the property exists.
+            let kind: SpecialKind = special_kind_property
+                .as_ref()
+                .unwrap()
+                .create(reader)?
+                .try_into()?;
+            kind.into()
+        }
+    })
+}
+
 struct LightCommonPath {
-    file_type: EntryType,
+    file_type: fuser::FileType,
     path: Vec<u8>,
+    ino: Ino,
 }
 
 struct LightCommonPathBuilder {
     store: jbk::reader::EntryStore,
     variant_id_property: jbk::reader::builder::VariantIdProperty,
     path_property: jbk::reader::builder::ArrayProperty,
+    file_hard_link_property: Option<jbk::reader::builder::IntProperty>,
+    special_kind_property: Option<jbk::reader::builder::IntProperty>,
 }
 
 impl LightCommonPathBuilder {
@@ -199,6 +226,8 @@ impl LightCommonPathBuilder {
             store: properties.store.clone(),
             variant_id_property: properties.variant_id_property,
             path_property: properties.path_property.clone(),
+            file_hard_link_property: properties.file_hard_link_property.clone(),
+            special_kind_property: properties.special_kind_property.clone(),
         }
     }
 }
@@ -211,8 +240,16 @@ impl jbk::reader::builder::BuilderTrait for LightCommonPathBuilder {
         let path_prop = self.path_property.create(&reader)?;
         let mut path = vec![];
         path_prop.resolve_to_vec(&mut path)?;
-        let file_type = self.variant_id_property.create(&reader)?.try_into()?;
-        Ok(LightCommonPath { file_type, path })
+        let entry_type = self.variant_id_property.create(&reader)?.try_into()?;
+        let ino = match entry_type {
+            EntryType::File => hard_link_ino(idx, &self.file_hard_link_property, &reader)?,
+            _ => Ino::from(idx),
+        };
+        Ok(LightCommonPath {
+            file_type: file_type(entry_type, &self.special_kind_property, &reader)?,
+            path,
+            ino,
+        })
     }
 }
 
@@ -245,6 +282,37 @@ impl jbk::reader::builder::BuilderTrait for LightCommonParentBuilder {
     }
 }
 
+struct LightXattrsBuilder {
+    store: jbk::reader::EntryStore,
+    xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
+}
+
+impl LightXattrsBuilder {
+    fn new(properties: &AllProperties) -> Self {
+        Self {
+            store: properties.store.clone(),
+            xattrs_property: properties.xattrs_property.clone(),
+        }
+    }
+}
+
+impl jbk::reader::builder::BuilderTrait for LightXattrsBuilder {
+    type Entry = crate::Xattrs;
+
+    fn create_entry(&self, idx: jbk::EntryIdx) -> jbk::Result<Self::Entry> {
+        match &self.xattrs_property {
+            None => Ok(crate::Xattrs::new()),
+            Some(xattrs_property) => {
+                let reader = self.store.get_entry_reader(idx);
+                let xattrs_prop = xattrs_property.create(&reader)?;
+                let mut xattrs = vec![];
+                xattrs_prop.resolve_to_vec(&mut xattrs)?;
+                crate::Xattrs::from_bytes(&xattrs)
+            }
+        }
+    }
+}
+
 struct AttrBuilder {
     store: jbk::reader::EntryStore,
     variant_id_property: jbk::reader::builder::VariantIdProperty,
@@ -252,9 +320,18 @@ struct AttrBuilder {
     group_property: jbk::reader::builder::IntProperty,
     rights_property: jbk::reader::builder::IntProperty,
     mtime_property: jbk::reader::builder::IntProperty,
+    mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+    atime_property: Option<jbk::reader::builder::IntProperty>,
+    ctime_property: Option<jbk::reader::builder::IntProperty>,
+    btime_property: Option<jbk::reader::builder::IntProperty>,
     file_size_property: jbk::reader::builder::IntProperty,
+    file_hard_link_property: Option<jbk::reader::builder::IntProperty>,
+    file_nlink_property: Option<jbk::reader::builder::IntProperty>,
     dir_nb_children_property: jbk::reader::builder::IntProperty,
     link_target_property: jbk::reader::builder::ArrayProperty,
+    special_kind_property: Option<jbk::reader::builder::IntProperty>,
+    special_major_property: Option<jbk::reader::builder::IntProperty>,
+    special_minor_property: Option<jbk::reader::builder::IntProperty>,
 }
 
 impl AttrBuilder {
@@ -266,13 +343,51 @@ impl AttrBuilder {
             group_property: properties.group_property.clone(),
             rights_property: properties.rigths_property.clone(),
             mtime_property: properties.mtime_property.clone(),
+            mtime_nsec_property: properties.mtime_nsec_property.clone(),
+            atime_property: properties.atime_property.clone(),
+            ctime_property: properties.ctime_property.clone(),
+            btime_property: properties.btime_property.clone(),
             file_size_property: properties.file_size_property.clone(),
+            file_hard_link_property: properties.file_hard_link_property.clone(),
+            file_nlink_property: properties.file_nlink_property.clone(),
             dir_nb_children_property: properties.dir_nb_children_property.clone(),
             link_target_property: properties.link_target_property.clone(),
+            special_kind_property: properties.special_kind_property.clone(),
+            special_major_property: properties.special_major_property.clone(),
+            special_minor_property: properties.special_minor_property.clone(),
         }
     }
 }
 
+/// Get the inode of the entry `idx`, hard links share the inode of their target.
+fn hard_link_ino(
+    idx: jbk::EntryIdx,
+    hard_link_property: &Option<jbk::reader::builder::IntProperty>,
+    reader: &jbk::reader::ByteSlice,
+) -> jbk::Result<Ino> {
+    Ok(match hard_link_property {
+        Some(p) => match p.create(reader)? {
+            0 => Ino::from(idx),
+            v => Ino::from(jbk::EntryIdx::from(v as u32 - 1)),
+        },
+        None => Ino::from(idx),
+    })
+}
+
+/// Read a optional time property (in nanoseconds, 0 meaning unknown).
+fn read_time(
+    property: &Option<jbk::reader::builder::IntProperty>,
+    reader: &jbk::reader::ByteSlice,
+) -> jbk::Result<Option<std::time::SystemTime>> {
+    Ok(match property {
+        Some(p) => match p.create(reader)? {
+            0 => None,
+            t => Some(std::time::UNIX_EPOCH + std::time::Duration::from_nanos(t)),
+        },
+        None => None,
+    })
+}
+
 fn div_ceil(value: u64, rhs: u64) -> u64 {
     let mut ret = value / rhs;
     if (value % rhs) != 0 {
@@ -302,28 +417,66 @@ impl jbk::reader::builder::BuilderTrait for AttrBuilder {
                     }
                 }
             }
+            EntryType::Special => 0,
         };
-        let rigths = (self.rights_property.create(&reader)? as u16) & 0b1111_1111_0110_1101;
+        // Filesystem is read only, remove write permissions.
+        let rigths = (self.rights_property.create(&reader)? & 0o7555) as u16;
         // Make kernel sync we allocate by block of 4KB.
         let allocated_size = match &kind {
-            EntryType::Dir => 0,
+            EntryType::Dir | EntryType::Special => 0,
             _ => div_ceil(size, 4 * 1024) * (4 * 1024),
         };
+        let rdev = match &kind {
+            EntryType::Special => {
+                let major = self
+                    .special_major_property
+                    .as_ref()
+                    .unwrap()
+                    .create(&reader)?;
+                let minor = self
+                    .special_minor_property
+                    .as_ref()
+                    .unwrap()
+                    .create(&reader)?;
+                let rdev: libc::dev_t = libc::makedev(major as _, minor as _);
+                // Fuse only transmits 32 bits device numbers.
+                u32::try_from(rdev).map_err(|_| {
+                    format!("Device number {major}:{minor} cannot be represented by fuse")
+                })?
+            }
+            _ => 0,
+        };
+        let (ino, nlink) = match &kind {
+            EntryType::File => (
+                hard_link_ino(idx, &self.file_hard_link_property, &reader)?,
+                match &self.file_nlink_property {
+                    Some(p) => p.create(&reader)? as u32,
+                    None => 1,
+                },
+            ),
+            _ => (Ino::from(idx), 1),
+        };
+        let mtime_nsec = match &self.mtime_nsec_property {
+            Some(p) => p.create(&reader)? as u32,
+            None => 0,
+        };
+        let mtime = std::time::UNIX_EPOCH
+            + std::time::Duration::new(self.mtime_property.create(&reader)?, mtime_nsec);
         Ok(fuser::FileAttr {
-            ino: Ino::from(idx).get(),
+            ino: ino.get(),
             size,
-            kind: kind.into(),
+            kind: file_type(kind, &self.special_kind_property, &reader)?,
             blocks: div_ceil(allocated_size, BLOCK_SIZE as u64),
-            atime: std::time::UNIX_EPOCH,
-            mtime: std::time::UNIX_EPOCH
-                + std::time::Duration::from_secs(self.mtime_property.create(&reader)?),
-            ctime: std::time::UNIX_EPOCH,
-            crtime: std::time::UNIX_EPOCH,
+            // Unknown access and change times default to modification time.
+            atime: read_time(&self.atime_property, &reader)?.unwrap_or(mtime),
+            mtime,
+            ctime: read_time(&self.ctime_property, &reader)?.unwrap_or(mtime),
+            crtime: read_time(&self.btime_property, &reader)?.unwrap_or(std::time::UNIX_EPOCH),
             perm: rigths,
-            nlink: 1,
+            nlink,
             uid: self.owner_property.create(&reader)? as u32,
             gid: self.group_property.create(&reader)? as u32,
-            rdev: 0,
+            rdev,
             blksize: BLOCK_SIZE,
             flags: 0,
         })
@@ -342,6 +495,7 @@ pub struct ArxFs<'a, S: Stats> {
     light_link_builder: LightLinkBuilder,
     light_common_path_builder: LightCommonPathBuilder,
     light_common_parent_builder: LightCommonParentBuilder,
+    light_xattrs_builder: LightXattrsBuilder,
     attr_builder: AttrBuilder,
     resolve_cache: LruCache<(Ino, OsString), Option<jbk::EntryIdx>, FxBuildHasher>,
     attr_cache: LruCache<jbk::EntryIdx, fuser::FileAttr, FxBuildHasher>,
@@ -373,6 +527,7 @@ impl<'a, S: Stats> ArxFs<'a, S> {
         let light_link_builder = LightLinkBuilder::new(&properties);
         let light_common_path_builder = LightCommonPathBuilder::new(&properties);
         let light_common_parent_builder = LightCommonParentBuilder::new(&properties);
+        let light_xattrs_builder = LightXattrsBuilder::new(&properties);
         let attr_builder = AttrBuilder::new(&properties);
         Ok(Self {
             arx,
@@ -384,6 +539,7 @@ impl<'a, S: Stats> ArxFs<'a, S> {
             light_link_builder,
             light_common_path_builder,
             light_common_parent_builder,
+            light_xattrs_builder,
             attr_builder,
             resolve_cache: LruCache::with_hasher(
                 NonZeroUsize::new(4 * 1024).unwrap(),
@@ -417,6 +573,14 @@ impl<'a, S: Stats> ArxFs<'a, S> {
         ]
     }
 
+    fn get_xattrs(&self, ino: Ino) -> jbk::Result<crate::Xattrs> {
+        match ino.try_into() {
+            // Root has no attributes
+            Err(_) => Ok(crate::Xattrs::new()),
+            Ok(idx) => self.entry_index.get_entry(&self.light_xattrs_builder, idx),
+        }
+    }
+
     pub fn mount<P: AsRef<Path>>(self, name: String, mount_point: P) -> jbk::Result<()> {
         let options = self.mount_options(name);
         fuser::mount2(self, &mount_point, &options)?;
@@ -435,6 +599,22 @@ impl<S: Stats + Send> ArxFs<'static, S> {
     }
 }
 
+#[cfg(target_os = "linux")]
+const ENOATTR: i32 = libc::ENODATA;
+#[cfg(not(target_os = "linux"))]
+const ENOATTR: i32 = libc::ENOATTR;
+
+/// Reply `data` to a xattr request, following the size protocol of getxattr/listxattr.
+fn reply_xattr(data: &[u8], size: u32, reply: fuser::ReplyXattr) {
+    if size == 0 {
+        reply.size(data.len() as u32);
+    } else if (size as usize) < data.len() {
+        reply.error(libc::ERANGE);
+    } else {
+        reply.data(data);
+    }
+}
+
 const ROOT_ATTR: fuser::FileAttr = fuser::FileAttr {
     ino: 1,
     size: 0,
@@ -573,6 +753,7 @@ impl<'a, S: Stats> fuser::Filesystem for ArxFs<'a, S> {
                         },
                         Err(EntryType::Dir) => reply.error(libc::EISDIR),
                         Err(EntryType::Link) => reply.error(libc::ENOENT), // [FIXME] What to return here ?
+                        Err(EntryType::Special) => reply.error(libc::ENXIO),
                         Err(EntryType::File) => unreachable!(),
                     }
                 }
@@ -690,12 +871,11 @@ impl<'a, S: Stats> fuser::Filesystem for ArxFs<'a, S> {
                         let entry = entry.unwrap();
                         // We remove "." and ".."
                         let entry_idx = range.offset() + jbk::EntryIdx::from(i as u32 - 2);
-                        let entry_ino = Ino::from(entry_idx);
                         let entry_path = OsString::from_vec(entry.path);
                         let should_break = reply.add(
-                            entry_ino.get(),
+                            entry.ino.get(),
                             /* offset =*/ i,
-                            entry.file_type.into(),
+                            entry.file_type,
                             &entry_path,
                         );
                         self.resolve_cache.put((ino, entry_path), Some(entry_idx));
@@ -720,4 +900,36 @@ impl<'a, S: Stats> fuser::Filesystem for ArxFs<'a, S> {
         self.stats.releasedir();
         reply.ok()
     }
+    fn getxattr(
+        &mut self,
+        _req: &fuser::Request,
+        ino: u64,
+        name: &OsStr,
+        size: u32,
+        reply: fuser::ReplyXattr,
+    ) {
+        self.stats.getxattr();
+        let xattrs = match self.get_xattrs(Ino::from(ino)) {
+            Ok(xattrs) => xattrs,
+            Err(_) => return reply.error(libc::EIO),
+        };
+        match xattrs.get(name.as_bytes()) {
+            None => reply.error(ENOATTR),
+            Some(value) => reply_xattr(value, size, reply),
+        }
+    }
+
+    fn listxattr(&mut self, _req: &fuser::Request, ino: u64, size: u32, reply: fuser::ReplyXattr) {
+        self.stats.listxattr();
+        let xattrs = match self.get_xattrs(Ino::from(ino)) {
+            Ok(xattrs) => xattrs,
+            Err(_) => return reply.error(libc::EIO),
+        };
+        let mut names = vec![];
+        for (name, _value) in xattrs.iter() {
+            names.extend_from_slice(name);
+            names.push(0);
+        }
+        reply_xattr(&names, size, reply)
+    }
 }
@@ -114,3 +114,24 @@ impl From<ConcatMode> for jbk::creator::ConcatMode {
         }
     }
 }
+
+/// Parse a size given in command line ("1024", "512K", "64M", "2G").
+///
+/// Suffixes are powers of 1024.
+pub fn size_arg_parser(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits_end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(digits_end);
+    let value: u64 = value.parse().map_err(|_| format!("Invalid size {s:?}"))?;
+    let multiplier = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(format!("Invalid size unit {unit:?}")),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Size {s:?} is too big"))
+}
@@ -38,6 +38,19 @@ pub trait FullBuilderTrait {
         idx: jbk::EntryIdx,
         reader: &ByteSlice,
     ) -> jbk::Result<<Self::Entry as EntryDef>::Dir>;
+
+    /// Create a special entry (device, fifo, socket).
+    ///
+    /// Builders which don't know about special entries fail on them.
+    fn create_special(
+        &self,
+        _idx: jbk::EntryIdx,
+        _reader: &ByteSlice,
+    ) -> jbk::Result<<Self::Entry as EntryDef>::Special> {
+        Err("Special entries are not supported by this builder"
+            .to_string()
+            .into())
+    }
 }
 
 impl<F, L, D> FullBuilderTrait for (F, L, D)
@@ -78,6 +91,64 @@ where
     ) -> jbk::Result<<Self::Entry as EntryDef>::Dir> {
         self.2.create_entry(idx, reader)
     }
+
+    fn create_special(
+        &self,
+        _idx: jbk::EntryIdx,
+        _reader: &ByteSlice,
+    ) -> jbk::Result<<Self::Entry as EntryDef>::Special> {
+        Ok(())
+    }
+}
+
+impl<F, L, D, S> FullBuilderTrait for (F, L, D, S)
+where
+    F: Builder,
+    L: Builder,
+    D: Builder,
+    S: Builder,
+{
+    type Entry = (F::Entry, L::Entry, D::Entry, S::Entry);
+
+    fn new(properties: &AllProperties) -> Self {
+        let file_builder = F::new(properties);
+        let link_builder = L::new(properties);
+        let dir_builder = D::new(properties);
+        let special_builder = S::new(properties);
+        (file_builder, link_builder, dir_builder, special_builder)
+    }
+
+    fn create_file(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> jbk::Result<<Self::Entry as EntryDef>::File> {
+        self.0.create_entry(idx, reader)
+    }
+
+    fn create_link(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> jbk::Result<<Self::Entry as EntryDef>::Link> {
+        self.1.create_entry(idx, reader)
+    }
+
+    fn create_dir(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> jbk::Result<<Self::Entry as EntryDef>::Dir> {
+        self.2.create_entry(idx, reader)
+    }
+
+    fn create_special(
+        &self,
+        idx: jbk::EntryIdx,
+        reader: &ByteSlice,
+    ) -> jbk::Result<<Self::Entry as EntryDef>::Special> {
+        self.3.create_entry(idx, reader)
+    }
 }
 
 pub(crate) struct RealBuilder<B: FullBuilderTrait> {
@@ -131,6 +202,10 @@ where
                 let entry = self.builder.create_dir(idx, &reader)?;
                 Entry::Dir(range, entry)
             }
+            EntryType::Special => {
+                let entry = self.builder.create_special(idx, &reader)?;
+                Entry::Special(entry)
+            }
         })
     }
 }
@@ -2,16 +2,26 @@ pub trait EntryDef {
     type File;
     type Link;
     type Dir;
+    type Special;
 }
 
 impl<F, L, D> EntryDef for (F, L, D) {
     type File = F;
     type Link = L;
     type Dir = D;
+    type Special = ();
+}
+
+impl<F, L, D, S> EntryDef for (F, L, D, S) {
+    type File = F;
+    type Link = L;
+    type Dir = D;
+    type Special = S;
 }
 
 pub enum Entry<E: EntryDef> {
     File(E::File),
     Link(E::Link),
     Dir(jbk::EntryRange, E::Dir),
+    Special(E::Special),
 }
@@ -4,16 +4,18 @@ pub enum EntryType {
     File = 0,
     Dir = 1,
     Link = 2,
+    Special = 3,
 }
 
 impl TryFrom<jbk::VariantIdx> for EntryType {
-    type Error = String;
+    type Error = crate::Error;
     fn try_from(id: jbk::VariantIdx) -> Result<Self, Self::Error> {
         match id.into_u8() {
             0 => Ok(Self::File),
             1 => Ok(Self::Dir),
             2 => Ok(Self::Link),
-            _ => Err("Invalid variant id".into()),
+            3 => Ok(Self::Special),
+            _ => Err(crate::Error::Format("Invalid variant id".into())),
         }
     }
 }
@@ -24,19 +26,44 @@ impl ToString for EntryType {
             EntryType::File => String::from("file"),
             EntryType::Dir => String::from("dir"),
             EntryType::Link => String::from("link"),
+            EntryType::Special => String::from("special"),
         }
     }
 }
 
 impl jbk::creator::VariantName for EntryType {}
 
+/// The kind of a special file (stored in the `Special` variant).
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[repr(u8)]
+pub enum SpecialKind {
+    CharDevice = 0,
+    BlockDevice = 1,
+    Fifo = 2,
+    Socket = 3,
+}
+
+impl TryFrom<u64> for SpecialKind {
+    type Error = crate::Error;
+    fn try_from(id: u64) -> Result<Self, Self::Error> {
+        match id {
+            0 => Ok(Self::CharDevice),
+            1 => Ok(Self::BlockDevice),
+            2 => Ok(Self::Fifo),
+            3 => Ok(Self::Socket),
+            _ => Err(crate::Error::Format("Invalid special kind".into())),
+        }
+    }
+}
+
 #[cfg(all(not(windows), feature = "fuse"))]
-impl From<EntryType> for fuser::FileType {
-    fn from(t: EntryType) -> Self {
-        match t {
-            EntryType::File => fuser::FileType::RegularFile,
-            EntryType::Dir => fuser::FileType::Directory,
-            EntryType::Link => fuser::FileType::Symlink,
+impl From<SpecialKind> for fuser::FileType {
+    fn from(k: SpecialKind) -> Self {
+        match k {
+            SpecialKind::CharDevice => fuser::FileType::CharDevice,
+            SpecialKind::BlockDevice => fuser::FileType::BlockDevice,
+            SpecialKind::Fifo => fuser::FileType::NamedPipe,
+            SpecialKind::Socket => fuser::FileType::Socket,
         }
     }
 }
@@ -2,14 +2,16 @@ mod builder;
 mod entry;
 mod entry_type;
 mod properties;
+mod xattrs;
 
 pub(crate) use builder::RealBuilder;
 pub use builder::{Builder, FullBuilderTrait};
 pub use entry::{Entry, EntryDef};
-pub use entry_type::EntryType;
+pub use entry_type::{EntryType, SpecialKind};
 use jbk::reader::builder::{BuilderTrait, PropertyBuilderTrait};
 use jbk::reader::Range;
 pub use properties::{AllProperties, Property};
+pub use xattrs::Xattrs;
 
 pub const VENDOR_ID: jbk::VendorId = jbk::VendorId::new([0x41, 0x52, 0x58, 0x00]);
 
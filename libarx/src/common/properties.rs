@@ -6,11 +6,22 @@ pub enum Property {
     Group,
     Rights,
     Mtime,
+    MtimeNsec,
+    Atime,
+    Ctime,
+    Btime,
+    Xattrs,
     Content,
     Size,
+    HardLink,
+    NLink,
+    Checksum,
     FirstChild,
     NbChildren,
     Target,
+    SpecialKind,
+    Major,
+    Minor,
 }
 
 impl ToString for Property {
@@ -23,11 +34,22 @@ impl ToString for Property {
             Group => "group",
             Rights => "rights",
             Mtime => "mtime",
+            MtimeNsec => "mtime_nsec",
+            Atime => "atime",
+            Ctime => "ctime",
+            Btime => "btime",
+            Xattrs => "xattrs",
             Content => "content",
             Size => "size",
+            HardLink => "hard_link",
+            NLink => "nlink",
+            Checksum => "checksum",
             FirstChild => "first_child",
             NbChildren => "nb_children",
             Target => "target",
+            SpecialKind => "kind",
+            Major => "major",
+            Minor => "minor",
         })
     }
 }
@@ -42,12 +64,23 @@ pub struct AllProperties {
     pub group_property: jbk::reader::builder::IntProperty,
     pub rigths_property: jbk::reader::builder::IntProperty,
     pub mtime_property: jbk::reader::builder::IntProperty,
+    pub mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+    pub atime_property: Option<jbk::reader::builder::IntProperty>,
+    pub ctime_property: Option<jbk::reader::builder::IntProperty>,
+    pub btime_property: Option<jbk::reader::builder::IntProperty>,
+    pub xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
     pub variant_id_property: jbk::reader::builder::VariantIdProperty,
     pub file_content_address_property: jbk::reader::builder::ContentProperty,
     pub file_size_property: jbk::reader::builder::IntProperty,
+    pub file_hard_link_property: Option<jbk::reader::builder::IntProperty>,
+    pub file_nlink_property: Option<jbk::reader::builder::IntProperty>,
+    pub file_checksum_property: Option<jbk::reader::builder::ArrayProperty>,
     pub dir_first_child_property: jbk::reader::builder::IntProperty,
     pub dir_nb_children_property: jbk::reader::builder::IntProperty,
     pub link_target_property: jbk::reader::builder::ArrayProperty,
+    pub special_kind_property: Option<jbk::reader::builder::IntProperty>,
+    pub special_major_property: Option<jbk::reader::builder::IntProperty>,
+    pub special_minor_property: Option<jbk::reader::builder::IntProperty>,
 }
 
 impl AllProperties {
@@ -60,19 +93,63 @@ impl AllProperties {
             variant_id_offset,
             variants,
             names,
-        } = layout.variant_part.as_ref().unwrap();
-        assert_eq!(variants.len(), 3);
+        } = layout
+            .variant_part
+            .as_ref()
+            .ok_or("Invalid arx archive: entries have no variant")?;
+        // Archives created with older versions of arx don't have the special variant.
+        if variants.len() != 3 && variants.len() != 4 {
+            return Err(format!(
+                "Invalid arx archive: entries have {} variants, expected 3 or 4",
+                variants.len()
+            )
+            .into());
+        }
         let path_property = (&layout.common["name"], value_storage).try_into()?;
         let parent_property = (&layout.common["parent"], value_storage).try_into()?;
         let owner_property = (&layout.common["owner"], value_storage).try_into()?;
         let group_property = (&layout.common["group"], value_storage).try_into()?;
         let rigths_property = (&layout.common["rights"], value_storage).try_into()?;
         let mtime_property = (&layout.common["mtime"], value_storage).try_into()?;
+        // Archives created with older versions of arx don't have extra times nor xattrs.
+        let mtime_nsec_property = match layout.common.get("mtime_nsec") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
+        let atime_property = match layout.common.get("atime") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
+        let ctime_property = match layout.common.get("ctime") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
+        let btime_property = match layout.common.get("btime") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
+        let xattrs_property = match layout.common.get("xattrs") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
         let variant_id_property = jbk::reader::builder::VariantIdProperty::new(*variant_id_offset);
         let file_content_address_property =
             (&variants[names["file"] as usize]["content"]).try_into()?;
         let file_size_property =
             (&variants[names["file"] as usize]["size"], value_storage).try_into()?;
+        // Archives created with older versions of arx don't have hard links.
+        let file_hard_link_property = match variants[names["file"] as usize].get("hard_link") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
+        let file_nlink_property = match variants[names["file"] as usize].get("nlink") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
+        let file_checksum_property = match variants[names["file"] as usize].get("checksum") {
+            Some(p) => Some((p, value_storage).try_into()?),
+            None => None,
+        };
         let dir_first_child_property = (
             &variants[names["dir"] as usize]["first_child"],
             value_storage,
@@ -85,6 +162,25 @@ impl AllProperties {
             .try_into()?;
         let link_target_property =
             (&variants[names["link"] as usize]["target"], value_storage).try_into()?;
+        let (special_kind_property, special_major_property, special_minor_property) =
+            match names.get("special") {
+                Some(special_id) => {
+                    let special = variants
+                        .get(*special_id as usize)
+                        .ok_or("Invalid arx archive: no special variant")?;
+                    let property = |name: &str| {
+                        special.get(name).ok_or_else(|| {
+                            format!("Invalid arx archive: special entries have no {name}")
+                        })
+                    };
+                    (
+                        Some((property("kind")?, value_storage).try_into()?),
+                        Some((property("major")?, value_storage).try_into()?),
+                        Some((property("minor")?, value_storage).try_into()?),
+                    )
+                }
+                None => (None, None, None),
+            };
         Ok(Self {
             store,
             path_property,
@@ -93,12 +189,23 @@ impl AllProperties {
             group_property,
             rigths_property,
             mtime_property,
+            mtime_nsec_property,
+            atime_property,
+            ctime_property,
+            btime_property,
+            xattrs_property,
             variant_id_property,
             file_content_address_property,
             file_size_property,
+            file_hard_link_property,
+            file_nlink_property,
+            file_checksum_property,
             dir_first_child_property,
             dir_nb_children_property,
             link_target_property,
+            special_kind_property,
+            special_major_property,
+            special_minor_property,
         })
     }
 }
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+/// Extended attributes of an entry.
+///
+/// Attributes are stored in the archive as a single array value. For each attribute,
+/// we store its name, a nul byte, the size of the value (u32 little endian) and the value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Xattrs(BTreeMap<Vec<u8>, Vec<u8>>);
+
+impl Xattrs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.0.get(name).map(|v| v.as_slice())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Iterate on (name, value) of the attributes, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.0.iter().map(|(n, v)| (n.as_slice(), v.as_slice()))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![];
+        for (name, value) in &self.0 {
+            data.extend_from_slice(name);
+            data.push(0);
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        data
+    }
+
+    pub fn from_bytes(mut data: &[u8]) -> jbk::Result<Self> {
+        let mut xattrs = Self::new();
+        while !data.is_empty() {
+            let name_end = match data.iter().position(|c| *c == 0) {
+                Some(p) => p,
+                None => return Err("Invalid xattrs: missing name end".into()),
+            };
+            let name = &data[..name_end];
+            data = &data[name_end + 1..];
+            if data.len() < 4 {
+                return Err("Invalid xattrs: missing value size".into());
+            }
+            let value_size = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+            data = &data[4..];
+            if data.len() < value_size {
+                return Err("Invalid xattrs: value is truncated".into());
+            }
+            xattrs.insert(name, &data[..value_size]);
+            data = &data[value_size..];
+        }
+        Ok(xattrs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() -> jbk::Result<()> {
+        let mut xattrs = Xattrs::new();
+        xattrs.insert(b"user.empty".as_slice(), b"".as_slice());
+        xattrs.insert(
+            b"security.capability".as_slice(),
+            [1, 0, 0, 2, 0, 32].as_slice(),
+        );
+        xattrs.insert(b"user.comment".as_slice(), b"Some comment".as_slice());
+        let data = xattrs.to_bytes();
+        assert_eq!(Xattrs::from_bytes(&data)?, xattrs);
+        assert_eq!(
+            xattrs.get(b"user.comment"),
+            Some(b"Some comment".as_slice())
+        );
+        let names: Vec<_> = xattrs.iter().map(|(n, _)| n).collect();
+        assert_eq!(
+            names,
+            vec![
+                b"security.capability".as_slice(),
+                b"user.comment".as_slice(),
+                b"user.empty".as_slice()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty() -> jbk::Result<()> {
+        assert!(Xattrs::new().to_bytes().is_empty());
+        assert!(Xattrs::from_bytes(b"")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Xattrs::from_bytes(b"user.foo").is_err());
+        assert!(Xattrs::from_bytes(b"user.foo\0\x05\0\0\0abc").is_err());
+    }
+}
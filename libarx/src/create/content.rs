@@ -0,0 +1,91 @@
+use jbk::creator::InputReader;
+use std::io::{Cursor, Read, Write};
+
+/// Default size above which [`read_content`] writes a content in a temporary file.
+pub const DEFAULT_SPILL_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Read a content from a stream (a member of a tar or zip archive, ...) to add it in an archive.
+///
+/// `size` is the expected size of the content. Contents up to `spill_threshold` bytes are read
+/// in memory, bigger ones are written in a temporary file. So a content never takes more than
+/// `spill_threshold` bytes in memory, whatever its size, but this doesn't bound the memory
+/// used by the creator itself.
+///
+/// Return the reader to give to the content adder, the size actually read and the checksum
+/// of the content if `compute_checksum` is true.
+pub fn read_content(
+    reader: &mut dyn Read,
+    size: u64,
+    spill_threshold: u64,
+    compute_checksum: bool,
+) -> crate::Result<(Box<dyn InputReader>, u64, Option<[u8; 32]>)> {
+    if size <= spill_threshold {
+        let mut data = Vec::with_capacity(size as usize);
+        reader.read_to_end(&mut data)?;
+        let checksum = compute_checksum.then(|| *blake3::hash(&data).as_bytes());
+        let size = data.len() as u64;
+        return Ok((Box::new(Cursor::new(data)), size, checksum));
+    }
+
+    let mut file = tempfile::tempfile()?;
+    let mut hasher = compute_checksum.then(blake3::Hasher::new);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut written = 0;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&buffer[..read]);
+        }
+        file.write_all(&buffer[..read])?;
+        written += read as u64;
+    }
+    let checksum = hasher.map(|h| *h.finalize().as_bytes());
+    Ok((
+        Box::new(jbk::creator::InputFile::new_range(file, 0, Some(written))?),
+        written,
+        checksum,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_content() -> crate::Result<()> {
+        // Bigger than the buffer used to copy to the temporary file.
+        let data = b"Some content to add".repeat(5000);
+        for spill_threshold in [data.len() as u64, 0] {
+            let (mut content, size, checksum) = read_content(
+                &mut data.as_slice(),
+                data.len() as u64,
+                spill_threshold,
+                true,
+            )?;
+            assert_eq!(size, data.len() as u64);
+            assert_eq!(checksum, Some(*blake3::hash(&data).as_bytes()));
+            let mut read = vec![];
+            content.read_to_end(&mut read)?;
+            assert_eq!(read, data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_content_size_mismatch() -> crate::Result<()> {
+        // The size actually read is returned, whatever the expected size.
+        let data = b"Some content";
+        for spill_threshold in [100, 0] {
+            let (_, size, checksum) =
+                read_content(&mut data.as_slice(), 5, spill_threshold, false)?;
+            assert_eq!(size, data.len() as u64);
+            assert_eq!(checksum, None);
+        }
+        Ok(())
+    }
+}
@@ -4,7 +4,8 @@ use std::sync::Arc;
 
 use jbk::creator::{BasicCreator, CachedContentAdder, ConcatMode, ContentAdder};
 
-use super::{EntryStoreCreator, EntryTrait, Void};
+use super::entry_store_creator::Lookup;
+use super::{EntryStoreCreator, EntryTrait, ImplicitDirPolicy, MetadataOverride, Void};
 
 pub struct SimpleCreator {
     cached_content_creator: CachedContentAdder<BasicCreator>,
@@ -18,7 +19,7 @@ impl SimpleCreator {
         progress: Arc<dyn jbk::creator::Progress>,
         cache_progress: Rc<dyn jbk::creator::CacheProgress>,
         compression: jbk::creator::Compression,
-    ) -> jbk::Result<Self> {
+    ) -> crate::Result<Self> {
         let basic_creator = BasicCreator::new(
             outfile,
             concat_mode,
@@ -38,15 +39,32 @@ impl SimpleCreator {
     }
 
     pub fn finalize(self, outfile: &Path) -> Void {
-        self.cached_content_creator
-            .into_inner()
-            .finalize(outfile, self.entry_store_creator, vec![])
+        Ok(self.cached_content_creator.into_inner().finalize(
+            outfile,
+            self.entry_store_creator,
+            vec![],
+        )?)
     }
 
     pub fn adder(&mut self) -> &mut impl ContentAdder {
         &mut self.cached_content_creator
     }
 
+    /// Normalize the metadata (owner, mode, times, ...) of the entries added from now on.
+    pub fn set_metadata_override(&mut self, metadata: MetadataOverride) {
+        self.entry_store_creator.set_metadata_override(metadata);
+    }
+
+    /// Set how the metadata of the directories created implicitly is set.
+    pub fn set_implicit_dir_policy(&mut self, policy: ImplicitDirPolicy) {
+        self.entry_store_creator.set_implicit_dir_policy(policy);
+    }
+
+    /// What is already added at `path`.
+    pub(crate) fn lookup(&self, path: &crate::Path) -> Lookup {
+        self.entry_store_creator.lookup(path)
+    }
+
     pub fn add_entry<E: EntryTrait>(&mut self, entry: &E) -> Void {
         self.entry_store_creator.add_entry(entry)
     }
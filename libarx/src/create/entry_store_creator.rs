@@ -1,9 +1,10 @@
-use crate::common::{EntryType, Property};
+use crate::common::{EntryType, Property, Xattrs};
 use jbk::creator::schema;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 
-use super::{EntryKind, EntryTrait, Void};
+use super::{EntryKind, EntryTrait, ImplicitDirPolicy, MetadataOverride, Void};
 
 type EntryStore = jbk::creator::EntryStore<
     Property,
@@ -17,6 +18,39 @@ type EntryIdx = jbk::Bound<jbk::EntryIdx>;
 enum DirOrFile {
     Dir(DirEntry),
     File(EntryIdx),
+    Regular(RegularFile),
+}
+
+/// A regular file added in the archive, which may be the target of hard links.
+#[derive(Clone)]
+struct RegularFile {
+    idx: EntryIdx,
+    size: jbk::Size,
+    content_address: jbk::ContentAddress,
+    checksum: Option<[u8; 32]>,
+    nlink: Arc<AtomicU64>,
+}
+
+impl RegularFile {
+    fn nlink_generator(&self) -> Box<dyn Fn() -> u64 + Sync + Send> {
+        let nlink = Arc::clone(&self.nlink);
+        Box::new(move || nlink.load(Ordering::Relaxed))
+    }
+}
+
+/// What is already added at a path.
+pub(crate) enum Lookup {
+    Missing,
+    Dir,
+    /// A file, link or special (or a path under one of them).
+    Other,
+}
+
+/// How the metadata of the added entries is set.
+#[derive(Default)]
+struct MetadataPolicy {
+    overrides: MetadataOverride,
+    implicit_dir: ImplicitDirPolicy,
 }
 
 /// A DirEntry structure to keep track of added direcotry in the archive.
@@ -50,6 +84,7 @@ impl DirEntry {
                 .values()
                 .map(|e| match e {
                     DirOrFile::File(i) => i.get().into_u64(),
+                    DirOrFile::Regular(f) => f.idx.get().into_u64(),
                     DirOrFile::Dir(e) => e.idx.as_ref().unwrap().get().into_u64(),
                 })
                 .min()
@@ -72,27 +107,92 @@ impl DirEntry {
         }
     }
 
-    fn add<'a, E, C>(&mut self, entry: &E, mut components: C, entry_store: &mut EntryStore) -> Void
+    fn add<'a, E, C>(
+        &mut self,
+        entry: &E,
+        mut components: C,
+        current_path: &crate::Path,
+        hard_link_target: Option<RegularFile>,
+        policy: &MetadataPolicy,
+        entry_store: &mut EntryStore,
+    ) -> Void
     where
         E: EntryTrait + ?Sized,
         C: Iterator<Item = relative_path::Component<'a>>,
     {
         match components.next() {
-            None => self.add_entry(entry, entry_store),
+            None => self.add_entry(entry, hard_link_target, &policy.overrides, entry_store),
             Some(component) => {
-                self.ensure_dir(component.as_str(), entry_store)?;
+                let current_path = current_path.join(component.as_str());
+                self.ensure_dir(
+                    component.as_str(),
+                    &current_path,
+                    entry,
+                    policy,
+                    entry_store,
+                )?;
                 let mut write_children = self.children.try_write().unwrap();
                 match write_children.get_mut(component.as_str()).unwrap() {
-                    DirOrFile::Dir(e) => e.add(entry, components, entry_store),
-                    DirOrFile::File(_) => {
-                        Err("Cannot add a entry to something which is not a directory".into())
+                    DirOrFile::Dir(e) => e.add(
+                        entry,
+                        components,
+                        &current_path,
+                        hard_link_target,
+                        policy,
+                        entry_store,
+                    ),
+                    DirOrFile::File(_) | DirOrFile::Regular(_) => {
+                        Err(crate::Error::NotADirectory(current_path))
                     }
                 }
             }
         }
     }
 
-    fn ensure_dir(&mut self, dir_name: &str, entry_store: &mut EntryStore) -> Void {
+    fn lookup<'a, C>(&self, mut components: C) -> Lookup
+    where
+        C: Iterator<Item = relative_path::Component<'a>>,
+    {
+        let component = match components.next() {
+            None => return Lookup::Dir,
+            Some(c) => c,
+        };
+        let children = self.children.try_read().unwrap();
+        match children.get(component.as_str()) {
+            None => Lookup::Missing,
+            Some(DirOrFile::Dir(e)) => e.lookup(components),
+            Some(_) => Lookup::Other,
+        }
+    }
+
+    /// Find the regular file at `components` (relative to self).
+    fn find_file<'a, C>(&self, mut components: C) -> Option<RegularFile>
+    where
+        C: Iterator<Item = relative_path::Component<'a>>,
+    {
+        let component = components.next()?;
+        let children = self.children.try_read().unwrap();
+        match children.get(component.as_str())? {
+            DirOrFile::Dir(e) => e.find_file(components),
+            DirOrFile::Regular(f) if components.next().is_none() => Some(f.clone()),
+            _ => None,
+        }
+    }
+
+    /// Create the directory `dir_name` (at `dir_path`) in self if it doesn't exist yet.
+    ///
+    /// `entry` is the entry being added in the directory.
+    fn ensure_dir<E>(
+        &mut self,
+        dir_name: &str,
+        dir_path: &crate::Path,
+        entry: &E,
+        policy: &MetadataPolicy,
+        entry_store: &mut EntryStore,
+    ) -> Void
+    where
+        E: EntryTrait + ?Sized,
+    {
         self.children
             .try_write()
             .unwrap()
@@ -100,6 +200,9 @@ impl DirEntry {
             .or_insert_with(|| {
                 let entry_idx = jbk::Vow::new(jbk::EntryIdx::from(0));
                 let dir_entry = DirEntry::new(entry_idx.bind());
+                let metadata = policy.implicit_dir.metadata(dir_path, entry);
+                let overrides = &policy.overrides;
+                let (mtime, mtime_nsec) = overrides.mtime(metadata.mtime, metadata.mtime_nsec);
                 let values = HashMap::from([
                     (
                         Property::Name,
@@ -109,10 +212,27 @@ impl DirEntry {
                         Property::Parent,
                         jbk::Value::UnsignedWord(self.as_parent_idx_generator().into()),
                     ),
-                    (Property::Owner, jbk::Value::Unsigned(1000)),
-                    (Property::Group, jbk::Value::Unsigned(1000)),
-                    (Property::Rights, jbk::Value::Unsigned(0o755)),
-                    (Property::Mtime, jbk::Value::Unsigned(0)),
+                    (
+                        Property::Owner,
+                        jbk::Value::Unsigned(overrides.uid(metadata.uid)),
+                    ),
+                    (
+                        Property::Group,
+                        jbk::Value::Unsigned(overrides.gid(metadata.gid)),
+                    ),
+                    (
+                        Property::Rights,
+                        jbk::Value::Unsigned(overrides.mode(metadata.mode)),
+                    ),
+                    (Property::Mtime, jbk::Value::Unsigned(mtime)),
+                    (Property::MtimeNsec, jbk::Value::Unsigned(mtime_nsec as u64)),
+                    (Property::Atime, jbk::Value::Unsigned(0)),
+                    (Property::Ctime, jbk::Value::Unsigned(0)),
+                    (Property::Btime, jbk::Value::Unsigned(0)),
+                    (
+                        Property::Xattrs,
+                        jbk::Value::Array(Xattrs::new().to_bytes().into()),
+                    ),
                     (
                         Property::FirstChild,
                         jbk::Value::UnsignedWord(dir_entry.first_entry_generator().into()),
@@ -136,7 +256,13 @@ impl DirEntry {
         Ok(())
     }
 
-    fn add_entry<E>(&mut self, entry: &E, entry_store: &mut EntryStore) -> Void
+    fn add_entry<E>(
+        &mut self,
+        entry: &E,
+        hard_link_target: Option<RegularFile>,
+        metadata: &MetadataOverride,
+        entry_store: &mut EntryStore,
+    ) -> Void
     where
         E: EntryTrait + ?Sized,
     {
@@ -150,6 +276,7 @@ impl DirEntry {
             .path()
             .file_name()
             .unwrap_or_else(|| panic!("{:?} has no file name", entry.path()));
+        let (mtime, mtime_nsec) = metadata.mtime(entry.mtime(), entry.mtime_nsec());
         let mut values = HashMap::from([
             (
                 Property::Name,
@@ -159,18 +286,50 @@ impl DirEntry {
                 Property::Parent,
                 jbk::Value::UnsignedWord(self.as_parent_idx_generator().into()),
             ),
-            (Property::Owner, jbk::Value::Unsigned(entry.uid())),
-            (Property::Group, jbk::Value::Unsigned(entry.gid())),
-            (Property::Rights, jbk::Value::Unsigned(entry.mode())),
-            (Property::Mtime, jbk::Value::Unsigned(entry.mtime())),
+            (
+                Property::Owner,
+                jbk::Value::Unsigned(metadata.uid(entry.uid())),
+            ),
+            (
+                Property::Group,
+                jbk::Value::Unsigned(metadata.gid(entry.gid())),
+            ),
+            (
+                Property::Rights,
+                jbk::Value::Unsigned(metadata.mode(entry.mode())),
+            ),
+            (Property::Mtime, jbk::Value::Unsigned(mtime)),
+            (Property::MtimeNsec, jbk::Value::Unsigned(mtime_nsec as u64)),
+            (
+                Property::Atime,
+                jbk::Value::Unsigned(metadata.extra_time(entry.atime_ns())),
+            ),
+            (
+                Property::Ctime,
+                jbk::Value::Unsigned(metadata.extra_time(entry.ctime_ns())),
+            ),
+            (
+                Property::Btime,
+                jbk::Value::Unsigned(metadata.extra_time(entry.btime_ns())),
+            ),
+            (
+                Property::Xattrs,
+                jbk::Value::Array(
+                    entry
+                        .xattrs()
+                        .map(|x| x.to_bytes())
+                        .unwrap_or_default()
+                        .into(),
+                ),
+            ),
         ]);
 
         match entry_kind {
             EntryKind::Dir => {
                 match self.children.try_read().unwrap().get(entry_name) {
                     Some(DirOrFile::Dir(_)) => return Ok(()),
-                    Some(DirOrFile::File(_)) => {
-                        return Err("Cannot add a dir when file or link already exists".into())
+                    Some(DirOrFile::File(_)) | Some(DirOrFile::Regular(_)) => {
+                        return Err(crate::Error::AlreadyExists(entry.path().to_owned()))
                     }
                     None => {}
                 };
@@ -203,10 +362,67 @@ impl DirEntry {
             }
             EntryKind::File(size, content_address) => {
                 if self.children.try_read().unwrap().contains_key(entry_name) {
-                    return Err("Cannot add a file when one already exists".into());
+                    return Err(crate::Error::AlreadyExists(entry.path().to_owned()));
                 }
+                let entry_idx = jbk::Vow::new(jbk::EntryIdx::from(0));
+                let file = RegularFile {
+                    idx: entry_idx.bind(),
+                    size,
+                    content_address,
+                    checksum: entry.checksum(),
+                    nlink: Arc::new(AtomicU64::new(1)),
+                };
                 values.insert(Property::Content, jbk::Value::Content(content_address));
                 values.insert(Property::Size, jbk::Value::Unsigned(size.into_u64()));
+                values.insert(Property::HardLink, jbk::Value::Unsigned(0));
+                values.insert(
+                    Property::Checksum,
+                    jbk::Value::Array(file.checksum.map_or(vec![], |c| c.to_vec()).into()),
+                );
+                values.insert(
+                    Property::NLink,
+                    jbk::Value::UnsignedWord(file.nlink_generator().into()),
+                );
+                let entry = Box::new(jbk::creator::BasicEntry::new_from_schema_idx(
+                    &entry_store.schema,
+                    entry_idx,
+                    Some(EntryType::File),
+                    values,
+                ));
+                entry_store.add_entry(entry);
+                self.children
+                    .try_write()
+                    .unwrap()
+                    .insert(entry_name.into(), DirOrFile::Regular(file));
+                Ok(())
+            }
+            EntryKind::HardLink(target) => {
+                if self.children.try_read().unwrap().contains_key(entry_name) {
+                    return Err(crate::Error::AlreadyExists(entry.path().to_owned()));
+                }
+                let file = match hard_link_target {
+                    Some(f) => f,
+                    // The target must be a regular file already added.
+                    None => return Err(crate::Error::NotFound(target)),
+                };
+                file.nlink.fetch_add(1, Ordering::Relaxed);
+                let target_idx = file.idx.clone();
+                let hard_link_generator: Box<dyn Fn() -> u64 + Sync + Send> =
+                    Box::new(move || target_idx.get().into_u64() + 1);
+                values.insert(Property::Content, jbk::Value::Content(file.content_address));
+                values.insert(Property::Size, jbk::Value::Unsigned(file.size.into_u64()));
+                values.insert(
+                    Property::Checksum,
+                    jbk::Value::Array(file.checksum.map_or(vec![], |c| c.to_vec()).into()),
+                );
+                values.insert(
+                    Property::HardLink,
+                    jbk::Value::UnsignedWord(hard_link_generator.into()),
+                );
+                values.insert(
+                    Property::NLink,
+                    jbk::Value::UnsignedWord(file.nlink_generator().into()),
+                );
                 let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
                     &entry_store.schema,
                     Some(EntryType::File),
@@ -221,7 +437,7 @@ impl DirEntry {
             }
             EntryKind::Link(target) => {
                 if self.children.try_read().unwrap().contains_key(entry_name) {
-                    return Err("Cannot add a link when one already exists".into());
+                    return Err(crate::Error::AlreadyExists(entry.path().to_owned()));
                 }
                 values.insert(Property::Target, jbk::Value::Array(target.into()));
                 let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
@@ -236,6 +452,25 @@ impl DirEntry {
                     .insert(entry_name.into(), DirOrFile::File(current_idx));
                 Ok(())
             }
+            EntryKind::Special(kind, major, minor) => {
+                if self.children.try_read().unwrap().contains_key(entry_name) {
+                    return Err(crate::Error::AlreadyExists(entry.path().to_owned()));
+                }
+                values.insert(Property::SpecialKind, jbk::Value::Unsigned(kind as u64));
+                values.insert(Property::Major, jbk::Value::Unsigned(major as u64));
+                values.insert(Property::Minor, jbk::Value::Unsigned(minor as u64));
+                let entry = Box::new(jbk::creator::BasicEntry::new_from_schema(
+                    &entry_store.schema,
+                    Some(EntryType::Special),
+                    values,
+                ));
+                let current_idx = entry_store.add_entry(entry);
+                self.children
+                    .try_write()
+                    .unwrap()
+                    .insert(entry_name.into(), DirOrFile::File(current_idx));
+                Ok(())
+            }
         }
     }
 }
@@ -243,12 +478,17 @@ impl DirEntry {
 pub struct EntryStoreCreator {
     entry_store: Box<EntryStore>,
     path_store: jbk::creator::StoreHandle,
+    xattr_store: jbk::creator::StoreHandle,
+    checksum_store: jbk::creator::StoreHandle,
     root_entry: DirEntry,
+    metadata_policy: MetadataPolicy,
 }
 
 impl EntryStoreCreator {
     pub fn new() -> Self {
         let path_store = jbk::creator::ValueStore::new_plain(None);
+        let xattr_store = jbk::creator::ValueStore::new_plain(None);
+        let checksum_store = jbk::creator::ValueStore::new_plain(None);
 
         let entry_def = schema::Schema::new(
             // Common part
@@ -259,6 +499,11 @@ impl EntryStoreCreator {
                 schema::Property::new_uint(Property::Group),  // group
                 schema::Property::new_uint(Property::Rights), // rights
                 schema::Property::new_uint(Property::Mtime),  // modification time
+                schema::Property::new_uint(Property::MtimeNsec), // sub-second part of modification time
+                schema::Property::new_uint(Property::Atime),     // access time in ns (0 if unknown)
+                schema::Property::new_uint(Property::Ctime), // status change time in ns (0 if unknown)
+                schema::Property::new_uint(Property::Btime), // birth time in ns (0 if unknown)
+                schema::Property::new_array(0, xattr_store.clone(), Property::Xattrs), // extended attributes
             ]),
             vec![
                 // File
@@ -267,6 +512,9 @@ impl EntryStoreCreator {
                     schema::VariantProperties::new(vec![
                         schema::Property::new_content_address(Property::Content),
                         schema::Property::new_uint(Property::Size), // Size
+                        schema::Property::new_uint(Property::HardLink), // index (+1) of the file we are a hard link to, 0 if none
+                        schema::Property::new_uint(Property::NLink), // number of hard links to the content
+                        schema::Property::new_array(0, checksum_store.clone(), Property::Checksum), // blake3 hash of the content (empty if not computed)
                     ]),
                 ),
                 // Directory
@@ -284,6 +532,15 @@ impl EntryStoreCreator {
                         schema::Property::new_array(1, path_store.clone(), Property::Target), // Id of the linked entry
                     ]),
                 ),
+                // Special (devices, fifos and sockets)
+                (
+                    EntryType::Special,
+                    schema::VariantProperties::new(vec![
+                        schema::Property::new_uint(Property::SpecialKind), // kind of special file
+                        schema::Property::new_uint(Property::Major),       // major device number
+                        schema::Property::new_uint(Property::Minor),       // minor device number
+                    ]),
+                ),
             ],
             Some(vec![Property::Parent, Property::Name]),
         );
@@ -295,26 +552,58 @@ impl EntryStoreCreator {
         Self {
             entry_store,
             path_store,
+            xattr_store,
+            checksum_store,
             root_entry,
+            metadata_policy: Default::default(),
         }
     }
 
+    /// Normalize the metadata of the entries added from now on.
+    pub fn set_metadata_override(&mut self, metadata: MetadataOverride) {
+        self.metadata_policy.overrides = metadata;
+    }
+
+    /// Set how the metadata of the directories created implicitly is set.
+    pub fn set_implicit_dir_policy(&mut self, policy: ImplicitDirPolicy) {
+        self.metadata_policy.implicit_dir = policy;
+    }
+
     pub fn entry_count(&self) -> jbk::EntryCount {
         jbk::EntryCount::from(self.root_entry.entry_count_generator()() as u32)
     }
 
+    /// What is already added at `path`.
+    pub(crate) fn lookup(&self, path: &crate::Path) -> Lookup {
+        self.root_entry.lookup(path.components())
+    }
+
     pub fn add_entry<E>(&mut self, entry: &E) -> Void
     where
         E: EntryTrait,
     {
         let path = entry.path();
+        let hard_link_target = match entry.kind()? {
+            Some(EntryKind::HardLink(target)) => self.root_entry.find_file(target.components()),
+            _ => None,
+        };
         match path.parent() {
-            None => self
-                .root_entry
-                .add(entry, std::iter::empty(), &mut self.entry_store),
-            Some(parent) => self
-                .root_entry
-                .add(entry, parent.components(), &mut self.entry_store),
+            None => self.root_entry.add(
+                entry,
+                std::iter::empty(),
+                crate::Path::new(""),
+                hard_link_target,
+                &self.metadata_policy,
+                &mut self.entry_store,
+            ),
+            Some(parent) => self.root_entry.add(
+                entry,
+                parent.components(),
+                crate::Path::new(""),
+                hard_link_target,
+                &self.metadata_policy,
+                &mut self.entry_store,
+            ),
         }
     }
 }
@@ -324,6 +613,8 @@ impl jbk::creator::EntryStoreTrait for EntryStoreCreator {
         let root_count = self.entry_count();
         let entry_count = self.entry_store.len();
         directory_pack.add_value_store(self.path_store);
+        directory_pack.add_value_store(self.xattr_store);
+        directory_pack.add_value_store(self.checksum_store);
         let entry_store_id = directory_pack.add_entry_store(self.entry_store);
         directory_pack.create_index(
             "arx_entries",
@@ -385,7 +676,7 @@ mod tests {
             &self.0
         }
 
-        fn kind(&self) -> jbk::Result<Option<EntryKind>> {
+        fn kind(&self) -> crate::Result<Option<EntryKind>> {
             Ok(Some(EntryKind::File(
                 jbk::Size::new(10),
                 jbk::ContentAddress::new(1.into(), 0.into()),
@@ -0,0 +1,171 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// Files and directories used by version control systems.
+const VCS_PATTERNS: &[&str] = &[
+    ".git",
+    ".gitignore",
+    ".gitattributes",
+    ".gitmodules",
+    ".hg",
+    ".hgignore",
+    ".hgtags",
+    ".svn",
+    ".bzr",
+    ".bzrignore",
+    "CVS",
+    ".cvsignore",
+    "_darcs",
+];
+
+/// Name of the files containing ignore rules read when walking with `respect_gitignore`.
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// Build a [`ExcludeFilter`].
+///
+/// Patterns use the gitignore syntax (`target/`, `*.o`, `/build`, `!keep.o`, ...).
+/// Patterns are relative to the current directory.
+pub struct ExcludeFilterBuilder {
+    patterns: GitignoreBuilder,
+    respect_gitignore: bool,
+}
+
+impl Default for ExcludeFilterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExcludeFilterBuilder {
+    pub fn new() -> Self {
+        Self {
+            patterns: GitignoreBuilder::new(""),
+            respect_gitignore: false,
+        }
+    }
+
+    /// Exclude entries matching `pattern`.
+    pub fn add_pattern(&mut self, pattern: &str) -> crate::Result<&mut Self> {
+        self.patterns
+            .add_line(None, pattern)
+            .map_err(|e| crate::Error::InvalidPath(format!("Invalid pattern {pattern}: {e}")))?;
+        Ok(self)
+    }
+
+    /// Exclude entries matching the patterns listed (one per line) in the file at `path`.
+    pub fn add_patterns_from_file(&mut self, path: &Path) -> crate::Result<&mut Self> {
+        // `GitignoreBuilder::add` silently ignores missing files.
+        let content = std::fs::read_to_string(path)?;
+        for line in content.lines() {
+            self.add_pattern(line)?;
+        }
+        Ok(self)
+    }
+
+    /// Exclude files and directories of version control systems (`.git`, `.svn`, ...).
+    pub fn exclude_vcs(&mut self) -> &mut Self {
+        for pattern in VCS_PATTERNS {
+            self.patterns
+                .add_line(None, pattern)
+                .expect("VCS patterns are valid");
+        }
+        self
+    }
+
+    /// Honour the `.gitignore` and `.ignore` files found in the walked directories.
+    pub fn respect_gitignore(&mut self, respect: bool) -> &mut Self {
+        self.respect_gitignore = respect;
+        self
+    }
+
+    pub fn build(&self) -> crate::Result<ExcludeFilter> {
+        let excludes = self
+            .patterns
+            .build()
+            .map_err(|e| crate::Error::InvalidPath(e.to_string()))?;
+        Ok(ExcludeFilter {
+            excludes,
+            respect_gitignore: self.respect_gitignore,
+            ignore_stack: vec![],
+        })
+    }
+}
+
+/// Exclude entries when adding files to an archive.
+///
+/// When walking directories, the filter keeps track of the ignore files found in the
+/// parent directories. So a filter must be used for only one walk (clone it for each walk).
+#[derive(Clone, Debug)]
+pub struct ExcludeFilter {
+    excludes: Gitignore,
+    respect_gitignore: bool,
+    // Ignore rules of the directories being walked, with their depth.
+    ignore_stack: Vec<(usize, Gitignore)>,
+}
+
+impl ExcludeFilter {
+    /// Return true if `path` (or one of its parents) is excluded by the patterns.
+    ///
+    /// Ignore files are not read, this is intended for entries not coming from a walk
+    /// (file lists).
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.excludes
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+
+    /// Return true if the entry must be kept.
+    ///
+    /// This is intended to be used as filter of [`crate::create::FsAdder::add_from_path_with_filter`].
+    /// Entries are expected in the order of the walk.
+    pub fn keep(&mut self, entry: &walkdir::DirEntry) -> bool {
+        let path = entry.path();
+        let is_dir = entry.file_type().is_dir();
+        while self
+            .ignore_stack
+            .last()
+            .is_some_and(|(depth, _)| *depth >= entry.depth())
+        {
+            self.ignore_stack.pop();
+        }
+
+        let mut matched = self.excludes.matched(path, is_dir);
+        // Rules of the deepest ignore file take precedence.
+        for (_, gitignore) in self.ignore_stack.iter().rev() {
+            if !matched.is_none() {
+                break;
+            }
+            matched = gitignore.matched(path, is_dir);
+        }
+        if matched.is_ignore() {
+            return false;
+        }
+
+        if is_dir && self.respect_gitignore {
+            if let Some(gitignore) = Self::read_ignore_files(path) {
+                self.ignore_stack.push((entry.depth(), gitignore));
+            }
+        }
+        true
+    }
+
+    fn read_ignore_files(dir: &Path) -> Option<Gitignore> {
+        let mut builder = GitignoreBuilder::new(dir);
+        for name in IGNORE_FILES {
+            let path = dir.join(name);
+            if path.is_file() {
+                if let Some(e) = builder.add(&path) {
+                    log::warn!("Error reading {}: {e}", path.display());
+                }
+            }
+        }
+        match builder.build() {
+            Ok(gitignore) if !gitignore.is_empty() => Some(gitignore),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Invalid ignore rules in {}: {e}", dir.display());
+                None
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use crate::create::update::archived_content_reader;
 use crate::create::{EntryKind, EntryTrait, SimpleCreator, Void};
+use crate::CommonEntry;
 use bstr::{BString, ByteVec};
 use jbk::creator::InputReader;
+use std::collections::{HashMap, VecDeque};
 use std::fs::Metadata;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
@@ -14,6 +17,8 @@ pub enum FsEntryKind {
     Dir,
     File(jbk::Size, jbk::ContentAddress),
     Link(PathBuf),
+    HardLink(crate::PathBuf),
+    Special(crate::SpecialKind, u32, u32),
     Other,
 }
 
@@ -22,6 +27,7 @@ pub enum DetectedEntryKind {
     Dir,
     File(u64, PathBuf),
     Link(PathBuf),
+    Special(crate::SpecialKind, u32, u32),
     Other,
 }
 
@@ -32,12 +38,57 @@ pub struct FsEntry {
     gid: u64,
     mode: u64,
     mtime: u64,
+    mtime_nsec: u32,
+    atime_ns: Option<u64>,
+    ctime_ns: Option<u64>,
+    btime_ns: Option<u64>,
+    checksum: Option<[u8; 32]>,
+    xattrs: crate::Xattrs,
 }
 
-fn detect_kind(
+/// Convert a (optional) system time to nanoseconds since epoch.
+fn to_ns(time: std::io::Result<std::time::SystemTime>) -> Option<u64> {
+    let duration = time.ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(duration.as_nanos() as u64)
+}
+
+#[cfg(unix)]
+fn read_xattrs(path: &std::path::Path, follow_symlink: bool) -> crate::Result<crate::Xattrs> {
+    let mut xattrs = crate::Xattrs::new();
+    let names = if follow_symlink {
+        xattr::list_deref(path)
+    } else {
+        xattr::list(path)
+    };
+    let names = match names {
+        Ok(names) => names,
+        // Filesystem doesn't support xattrs, there is nothing to read.
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => return Ok(xattrs),
+        Err(e) => return Err(e.into()),
+    };
+    for name in names {
+        let value = if follow_symlink {
+            xattr::get_deref(path, &name)?
+        } else {
+            xattr::get(path, &name)?
+        };
+        // Attribute may have been removed since we list them.
+        if let Some(value) = value {
+            xattrs.insert(name.into_encoded_bytes(), value);
+        }
+    }
+    Ok(xattrs)
+}
+
+#[cfg(windows)]
+fn read_xattrs(_path: &std::path::Path, _follow_symlink: bool) -> crate::Result<crate::Xattrs> {
+    Ok(crate::Xattrs::new())
+}
+
+pub(crate) fn detect_kind(
     mut path: PathBuf,
     follow_symlink: bool,
-) -> jbk::Result<(DetectedEntryKind, Metadata)> {
+) -> crate::Result<(DetectedEntryKind, Metadata)> {
     log::trace!("std::fs::symlink_metadata({path:?})");
     let mut attr = std::fs::symlink_metadata(&path)?;
     log::trace!("=> {attr:?}");
@@ -55,38 +106,263 @@ fn detect_kind(
     } else if attr.is_symlink() {
         DetectedEntryKind::Link(path)
     } else {
-        DetectedEntryKind::Other
+        detect_special(&attr)
     };
     Ok((kind, attr))
 }
 
-impl FsEntry {
-    pub fn new_from_path<A: jbk::creator::ContentAdder>(
+#[cfg(unix)]
+fn detect_special(attr: &Metadata) -> DetectedEntryKind {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = attr.file_type();
+    let rdev = attr.rdev() as libc::dev_t;
+    let (major, minor) = (libc::major(rdev) as u32, libc::minor(rdev) as u32);
+    if file_type.is_char_device() {
+        DetectedEntryKind::Special(crate::SpecialKind::CharDevice, major, minor)
+    } else if file_type.is_block_device() {
+        DetectedEntryKind::Special(crate::SpecialKind::BlockDevice, major, minor)
+    } else if file_type.is_fifo() {
+        DetectedEntryKind::Special(crate::SpecialKind::Fifo, 0, 0)
+    } else if file_type.is_socket() {
+        DetectedEntryKind::Special(crate::SpecialKind::Socket, 0, 0)
+    } else {
+        DetectedEntryKind::Other
+    }
+}
+
+#[cfg(windows)]
+fn detect_special(_attr: &Metadata) -> DetectedEntryKind {
+    DetectedEntryKind::Other
+}
+
+/// Files already added with several links, indexed by (device, inode).
+pub type HardLinkMap = HashMap<(u64, u64), crate::PathBuf>;
+
+/// Return the path of the already added file `attr` is a hard link to.
+/// If there is none, remember `arx_path` as the target of future hard links.
+#[cfg(unix)]
+fn detect_hard_link(
+    attr: &Metadata,
+    arx_path: &crate::Path,
+    hard_links: &mut HardLinkMap,
+) -> Option<crate::PathBuf> {
+    if attr.nlink() <= 1 {
+        return None;
+    }
+    match hard_links.entry((attr.dev(), attr.ino())) {
+        std::collections::hash_map::Entry::Occupied(e) => Some(e.get().clone()),
+        std::collections::hash_map::Entry::Vacant(e) => {
+            e.insert(arx_path.to_owned());
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+fn detect_hard_link(
+    _attr: &Metadata,
+    _arx_path: &crate::Path,
+    _hard_links: &mut HardLinkMap,
+) -> Option<crate::PathBuf> {
+    None
+}
+
+/// Files smaller than this are read in memory when prepared.
+const IN_MEMORY_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Maximum number of entries prepared ahead of the entry being added.
+///
+/// As only small files are read in memory, this also bounds the memory used
+/// (`PREPARE_QUEUE_SIZE * IN_MEMORY_MAX_SIZE`).
+const PREPARE_QUEUE_SIZE: usize = 32;
+
+/// The content of a file, read (and hashed) but not added yet.
+enum FileContent {
+    InMemory(Vec<u8>),
+    OnDisk(PathBuf),
+    /// A big file already opened and hashed (with the hashed size).
+    /// It is read again from the same descriptor when added.
+    Opened(std::fs::File, u64),
+    /// The file is unchanged since the reference archive, its content is taken from it.
+    Archived(jbk::ContentAddress, u64),
+}
+
+impl FileContent {
+    fn read(
+        path: &std::path::Path,
+        size: u64,
+        compute_checksum: bool,
+    ) -> crate::Result<(Self, Option<[u8; 32]>)> {
+        if size < IN_MEMORY_MAX_SIZE {
+            let content = std::fs::read(path)?;
+            let checksum = compute_checksum.then(|| *blake3::hash(&content).as_bytes());
+            Ok((Self::InMemory(content), checksum))
+        } else if compute_checksum {
+            // Keep the descriptor used to hash the file, so the added content is the hashed one
+            // even if the file is replaced meanwhile.
+            let mut file = std::fs::File::open(path)?;
+            let mut hasher = blake3::Hasher::new();
+            let size = std::io::copy(&mut file, &mut hasher)?;
+            Ok((
+                Self::Opened(file, size),
+                Some(*hasher.finalize().as_bytes()),
+            ))
+        } else {
+            // The file will be read only when added.
+            Ok((Self::OnDisk(path.to_path_buf()), None))
+        }
+    }
+
+    fn into_reader(self, reference: Option<&crate::Arx>) -> crate::Result<Box<dyn InputReader>> {
+        Ok(match self {
+            Self::InMemory(content) => Box::new(Cursor::new(content)),
+            Self::OnDisk(path) => Box::new(jbk::creator::InputFile::open(&path)?),
+            Self::Opened(file, size) => {
+                Box::new(jbk::creator::InputFile::new_range(file, 0, Some(size))?)
+            }
+            Self::Archived(content, size) => archived_content_reader(
+                reference.expect("Archived content comes from the reference"),
+                content,
+                size,
+            )?,
+        })
+    }
+}
+
+/// The modification time (seconds and nanoseconds) of a file.
+#[cfg(unix)]
+pub(crate) fn fs_mtime(attr: &Metadata) -> (u64, u32) {
+    (attr.mtime() as u64, attr.mtime_nsec() as u32)
+}
+
+#[cfg(windows)]
+pub(crate) fn fs_mtime(attr: &Metadata) -> (u64, u32) {
+    (
+        epochs::to_unix(epochs::windows_file(attr.last_write_time() as i64).unwrap()) as u64,
+        to_ns(attr.modified()).map_or(0, |t| (t % 1_000_000_000) as u32),
+    )
+}
+
+#[cfg(unix)]
+fn may_be_hard_link(attr: &Metadata) -> bool {
+    attr.nlink() > 1
+}
+
+#[cfg(windows)]
+fn may_be_hard_link(_attr: &Metadata) -> bool {
+    false
+}
+
+/// A entry read from the filesystem, not added yet to the archive.
+///
+/// Preparing entries (reading and hashing files) is independent of the archive,
+/// so several entries can be prepared in parallel. Prepared entries must then be
+/// converted with [`PreparedEntry::into_entry`] in the order they must be added.
+pub struct PreparedEntry {
+    fs_path: PathBuf,
+    arx_path: crate::PathBuf,
+    kind: DetectedEntryKind,
+    attr: Metadata,
+    // None if the file may be a hard link, content will be read only if needed.
+    content: Option<(FileContent, Option<[u8; 32]>)>,
+    compute_checksum: bool,
+    xattrs: crate::Xattrs,
+}
+
+impl PreparedEntry {
+    /// Prepare the entry at `fs_path`.
+    ///
+    /// `reference` is the file at the same path in a reference archive. If the file has the
+    /// same size and mtime, it is considered unchanged and its content is not read.
+    pub fn new(
         fs_path: &std::path::Path,
         arx_path: crate::PathBuf,
-        adder: &mut A,
         follow_symlink: bool,
-    ) -> jbk::Result<Box<Self>> {
+        compute_checksum: bool,
+        reference: Option<crate::FileEntry>,
+    ) -> crate::Result<Self> {
         let (kind, attr) = detect_kind(fs_path.to_path_buf(), follow_symlink)?;
-        let kind = match kind {
-            DetectedEntryKind::Dir => FsEntryKind::Dir,
-            DetectedEntryKind::File(file_size, path) => {
-                let reader: Box<dyn InputReader> = if file_size < 1024 * 1024 {
-                    let content = std::fs::read(&path)?;
-                    Box::new(Cursor::new(content))
-                } else {
-                    Box::new(jbk::creator::InputFile::open(&path)?)
+        let unchanged = match (&kind, &reference) {
+            (DetectedEntryKind::File(size, _), Some(r)) => {
+                r.size().into_u64() == *size
+                    && (r.mtime(), r.mtime_nsec()) == fs_mtime(&attr)
+                    && (!compute_checksum || r.checksum().is_some())
+            }
+            _ => false,
+        };
+        let content = match (&kind, reference) {
+            (DetectedEntryKind::File(size, _), Some(reference)) if unchanged => Some((
+                FileContent::Archived(reference.content(), *size),
+                reference.checksum().copied(),
+            )),
+            (DetectedEntryKind::File(size, path), _) if !may_be_hard_link(&attr) => {
+                Some(FileContent::read(path, *size, compute_checksum)?)
+            }
+            _ => None,
+        };
+        let xattrs = read_xattrs(fs_path, follow_symlink)?;
+        Ok(Self {
+            fs_path: fs_path.to_path_buf(),
+            arx_path,
+            kind,
+            attr,
+            content,
+            compute_checksum,
+            xattrs,
+        })
+    }
+
+    /// Add the content of the entry with `adder` and create the entry to add.
+    ///
+    /// `reference` is the archive given when preparing the entry.
+    pub fn into_entry<A: jbk::creator::ContentAdder>(
+        self,
+        adder: &mut A,
+        hard_links: &mut HardLinkMap,
+        reference: Option<&crate::Arx>,
+    ) -> crate::Result<Box<FsEntry>> {
+        let mut checksum = None;
+        let hard_link_target = match self.kind {
+            DetectedEntryKind::File(_, _) => {
+                detect_hard_link(&self.attr, &self.arx_path, hard_links)
+            }
+            _ => None,
+        };
+        let kind = match (self.kind, hard_link_target) {
+            (DetectedEntryKind::File(_, _), Some(target)) => FsEntryKind::HardLink(target),
+            (DetectedEntryKind::Dir, _) => FsEntryKind::Dir,
+            (DetectedEntryKind::File(file_size, path), None) => {
+                let (content, file_checksum) = match self.content {
+                    Some(c) => c,
+                    None => FileContent::read(&path, file_size, self.compute_checksum)?,
                 };
-                let content_address = adder.add_content(reader, jbk::creator::CompHint::Detect)?;
+                checksum = file_checksum;
+                let content_address = adder.add_content(
+                    content.into_reader(reference)?,
+                    jbk::creator::CompHint::Detect,
+                )?;
                 FsEntryKind::File(file_size.into(), content_address)
             }
-            DetectedEntryKind::Link(path) => FsEntryKind::Link(std::fs::read_link(&path)?),
-            DetectedEntryKind::Other => FsEntryKind::Other,
+            (DetectedEntryKind::Link(path), _) => FsEntryKind::Link(std::fs::read_link(&path)?),
+            (DetectedEntryKind::Special(kind, major, minor), _) => {
+                FsEntryKind::Special(kind, major, minor)
+            }
+            (DetectedEntryKind::Other, _) => {
+                log::warn!(
+                    "{} has an unknown file type, it is not added",
+                    self.fs_path.display()
+                );
+                FsEntryKind::Other
+            }
         };
-        log::debug!("{fs_path:?} is dectected as a {kind:?}");
-        Ok(Box::new(Self {
+        log::debug!("{:?} is dectected as a {kind:?}", self.fs_path);
+        let attr = self.attr;
+        let (mtime, mtime_nsec) = fs_mtime(&attr);
+        Ok(Box::new(FsEntry {
             kind,
-            arx_path,
+            arx_path: self.arx_path,
+            checksum,
+            xattrs: self.xattrs,
             #[cfg(unix)]
             uid: attr.uid() as u64,
             #[cfg(windows)]
@@ -98,18 +374,35 @@ impl FsEntry {
             #[cfg(unix)]
             mode: attr.mode() as u64,
             #[cfg(windows)]
-            mode: 755,
+            mode: 0o755,
+            mtime,
+            mtime_nsec,
+            atime_ns: to_ns(attr.accessed()),
             #[cfg(unix)]
-            mtime: attr.mtime() as u64,
+            ctime_ns: Some(attr.ctime() as u64 * 1_000_000_000 + attr.ctime_nsec() as u64),
             #[cfg(windows)]
-            mtime: epochs::to_unix(epochs::windows_file(attr.last_write_time() as i64).unwrap())
-                as u64,
+            ctime_ns: None,
+            btime_ns: to_ns(attr.created()),
         }))
     }
 }
 
+impl FsEntry {
+    pub fn new_from_path<A: jbk::creator::ContentAdder>(
+        fs_path: &std::path::Path,
+        arx_path: crate::PathBuf,
+        adder: &mut A,
+        follow_symlink: bool,
+        hard_links: &mut HardLinkMap,
+        compute_checksum: bool,
+    ) -> crate::Result<Box<Self>> {
+        PreparedEntry::new(fs_path, arx_path, follow_symlink, compute_checksum, None)?
+            .into_entry(adder, hard_links, None)
+    }
+}
+
 impl EntryTrait for FsEntry {
-    fn kind(&self) -> jbk::Result<Option<EntryKind>> {
+    fn kind(&self) -> crate::Result<Option<EntryKind>> {
         Ok(match &self.kind {
             FsEntryKind::Dir => Some(EntryKind::Dir),
             FsEntryKind::File(size, content_address) => {
@@ -120,7 +413,11 @@ impl EntryTrait for FsEntry {
                 Vec::from_path_buf(target.clone())
                     .unwrap_or_else(|target| panic!("{target:?} must be utf-8")),
             ))),
-            _ => None,
+            FsEntryKind::HardLink(target) => Some(EntryKind::HardLink(target.clone())),
+            FsEntryKind::Special(kind, major, minor) => {
+                Some(EntryKind::Special(*kind, *major, *minor))
+            }
+            FsEntryKind::Other => None,
         })
     }
     fn path(&self) -> &crate::Path {
@@ -139,11 +436,32 @@ impl EntryTrait for FsEntry {
     fn mtime(&self) -> u64 {
         self.mtime
     }
+    fn mtime_nsec(&self) -> u32 {
+        self.mtime_nsec
+    }
+    fn atime_ns(&self) -> Option<u64> {
+        self.atime_ns
+    }
+    fn ctime_ns(&self) -> Option<u64> {
+        self.ctime_ns
+    }
+    fn btime_ns(&self) -> Option<u64> {
+        self.btime_ns
+    }
+    fn checksum(&self) -> Option<[u8; 32]> {
+        self.checksum
+    }
+    fn xattrs(&self) -> Option<&crate::Xattrs> {
+        Some(&self.xattrs)
+    }
 }
 
 pub struct FsAdder<'a> {
     creator: &'a mut SimpleCreator,
     strip_prefix: crate::PathBuf,
+    hard_links: HardLinkMap,
+    checksum: bool,
+    reference: Option<&'a crate::Arx>,
 }
 
 impl<'a> FsAdder<'a> {
@@ -151,9 +469,23 @@ impl<'a> FsAdder<'a> {
         Self {
             creator,
             strip_prefix,
+            hard_links: Default::default(),
+            checksum: false,
+            reference: None,
         }
     }
 
+    /// Use `arx` as reference: files with the same size and mtime than the file at the
+    /// same path in `arx` are not read, their content is copied from `arx`.
+    pub fn set_reference(&mut self, arx: &'a crate::Arx) {
+        self.reference = Some(arx);
+    }
+
+    /// Compute and store a checksum (blake3) of the content of added files.
+    pub fn set_checksum(&mut self, checksum: bool) {
+        self.checksum = checksum;
+    }
+
     pub fn add_from_path<P>(&mut self, path: P, recurse: bool) -> Void
     where
         P: AsRef<std::path::Path>,
@@ -169,11 +501,12 @@ impl<'a> FsAdder<'a> {
     {
         let path = path.as_ref();
         log::trace!("add_from_path_with_filter(path:{path:?}, recurse:{recurse})");
-        let (tx, rx) = mpsc::channel();
+        let (tx, rx) = mpsc::sync_channel(PREPARE_QUEUE_SIZE);
         let path_copy = path.to_path_buf();
 
         spawn(move || {
-            let mut walker = walkdir::WalkDir::new(path_copy);
+            // Sort entries so the archive doesn't depend on the order of the filesystem.
+            let mut walker = walkdir::WalkDir::new(path_copy).sort_by_file_name();
 
             if !recurse {
                 walker = walker.max_depth(0);
@@ -181,38 +514,89 @@ impl<'a> FsAdder<'a> {
             let walker = walker.into_iter();
             for entry in walker.filter_entry(filter) {
                 let entry = entry.unwrap();
-                tx.send(entry).unwrap();
+                // Receiver is dropped if adding a entry failed.
+                if tx.send(entry).is_err() {
+                    break;
+                }
             }
         });
 
-        while let Ok(entry) = rx.recv() {
+        self.add_paths(rx.into_iter().map(|entry| {
             // We always want to follow link if this is the root entry.
             // This allow user to create a link to a file/dir to add the entry under a different name.
             // Walkdir will do the same anyway if it is a directory.
             let is_root_entry = entry.path() == path;
-
-            self.add_entry_from_path(entry.path(), is_root_entry)?;
-        }
-        Ok(())
+            (entry.into_path(), is_root_entry)
+        }))
     }
 
     pub fn add_from_list<Iter>(&mut self, paths: Iter, follow_symlink: bool) -> Void
     where
         Iter: Iterator<Item = std::path::PathBuf>,
     {
-        for path in paths {
-            self.add_entry_from_path(&path, follow_symlink)?;
+        self.add_paths(paths.map(|path| (path, follow_symlink)))
+    }
+
+    /// Prepare the entries at `paths` in parallel and add them in order.
+    ///
+    /// Entries are prepared in the rayon thread pool while the previous ones are added.
+    /// At most `PREPARE_QUEUE_SIZE` entries are prepared ahead of the one being added.
+    fn add_paths<Iter>(&mut self, paths: Iter) -> Void
+    where
+        Iter: Iterator<Item = (PathBuf, bool)>,
+    {
+        let mut queue = VecDeque::with_capacity(PREPARE_QUEUE_SIZE);
+        for (path, follow_symlink) in paths {
+            log::debug!("add_path(path:{path:?}, follow_symlink:{follow_symlink})");
+            let arx_path = match self.arx_path(&path)? {
+                Some(arx_path) => arx_path,
+                None => continue,
+            };
+            if queue.len() == PREPARE_QUEUE_SIZE {
+                self.add_prepared(queue.pop_front().unwrap())?;
+            }
+            let reference = self.reference_file(&arx_path);
+            let checksum = self.checksum;
+            let (tx, rx) = mpsc::sync_channel(1);
+            rayon::spawn(move || {
+                let entry =
+                    PreparedEntry::new(&path, arx_path, follow_symlink, checksum, reference);
+                // Receiver is dropped if adding a previous entry failed.
+                let _ = tx.send(entry);
+            });
+            queue.push_back(rx);
+        }
+        while let Some(prepared) = queue.pop_front() {
+            self.add_prepared(prepared)?;
         }
         Ok(())
     }
 
-    pub fn add_entry_from_path(&mut self, path: &std::path::Path, follow_symlink: bool) -> Void {
-        log::debug!("add_path(path:{path:?}, follow_symlink:{follow_symlink})");
+    /// Wait for the entry prepared in the thread pool and add it.
+    fn add_prepared(&mut self, prepared: mpsc::Receiver<crate::Result<PreparedEntry>>) -> Void {
+        let entry = prepared
+            .recv()
+            .expect("Preparing thread always sends the prepared entry")?;
+        let entry = entry.into_entry(self.creator.adder(), &mut self.hard_links, self.reference)?;
+        self.creator.add_entry(entry.as_ref())
+    }
 
+    /// The file at `arx_path` in the reference archive.
+    fn reference_file(&self, arx_path: &crate::Path) -> Option<crate::FileEntry> {
+        match self.reference?.get_entry::<crate::FullBuilder>(arx_path) {
+            Ok(crate::Entry::File(file)) => Some(file),
+            _ => None,
+        }
+    }
+
+    /// The path in the archive of the file at `path`.
+    ///
+    /// Return None if nothing is left once `strip_prefix` is removed.
+    fn arx_path(&self, path: &std::path::Path) -> crate::Result<Option<crate::PathBuf>> {
         let arx_path = match crate::PathBuf::from_path(path) {
             Ok(p) => p,
             Err(e) => {
-                return Err(match e.kind() {
+                return Err(crate::Error::InvalidPath(match e.kind() {
                     relative_path::FromPathErrorKind::NonRelative => {
                         format!("{} is not a relative path", path.display())
                     }
@@ -228,20 +612,27 @@ impl<'a> FsAdder<'a> {
                             path.display()
                         )
                     }
-                }
-                .into())
+                }))
             }
         };
         let arx_path: crate::PathBuf = match arx_path.strip_prefix(&self.strip_prefix) {
             Ok(p) => p,
-            Err(_e) => return Err(format!("{} is not in {arx_path}", self.strip_prefix).into()),
+            Err(_e) => {
+                return Err(crate::Error::InvalidPath(format!(
+                    "{arx_path} is not in {}",
+                    self.strip_prefix
+                )))
+            }
         }
         .into();
         if arx_path.as_str().is_empty() {
-            return Ok(());
+            Ok(None)
+        } else {
+            Ok(Some(arx_path))
         }
-        let entry = FsEntry::new_from_path(path, arx_path, self.creator.adder(), follow_symlink)?;
+    }
 
-        self.creator.add_entry(entry.as_ref())
+    pub fn add_entry_from_path(&mut self, path: &std::path::Path, follow_symlink: bool) -> Void {
+        self.add_paths(std::iter::once((path.to_path_buf(), follow_symlink)))
     }
 }
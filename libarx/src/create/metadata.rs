@@ -0,0 +1,209 @@
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use super::EntryTrait;
+
+/// Normalize the metadata of the entries added in an archive.
+///
+/// This is mainly used to create reproducible archives: the metadata stored in the archive
+/// does not depend on who creates the archive or when.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataOverride {
+    /// Store this owner instead of the owner of the entries.
+    pub owner: Option<u64>,
+
+    /// Store this group instead of the group of the entries.
+    pub group: Option<u64>,
+
+    /// Bits to clear from the mode of the entries (as a umask).
+    pub mode_mask: u64,
+
+    /// Modification times later than this time (in seconds since epoch) are set to it.
+    ///
+    /// This is typically set from `SOURCE_DATE_EPOCH`.
+    pub mtime_clamp: Option<u64>,
+
+    /// Do not store access, status change and birth times.
+    pub drop_extra_times: bool,
+}
+
+impl MetadataOverride {
+    /// Read the `SOURCE_DATE_EPOCH` environment variable.
+    ///
+    /// See <https://reproducible-builds.org/specs/source-date-epoch/>.
+    pub fn source_date_epoch() -> crate::Result<Option<u64>> {
+        match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(value) => value.trim().parse().map(Some).map_err(|_| {
+                crate::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid SOURCE_DATE_EPOCH value {value:?}"),
+                ))
+            }),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(e) => Err(crate::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid SOURCE_DATE_EPOCH value: {e}"),
+            ))),
+        }
+    }
+
+    pub(crate) fn uid(&self, uid: u64) -> u64 {
+        self.owner.unwrap_or(uid)
+    }
+
+    pub(crate) fn gid(&self, gid: u64) -> u64 {
+        self.group.unwrap_or(gid)
+    }
+
+    pub(crate) fn mode(&self, mode: u64) -> u64 {
+        mode & !self.mode_mask & 0o7777
+    }
+
+    /// The modification time (seconds and nanoseconds) to store.
+    pub(crate) fn mtime(&self, mtime: u64, mtime_nsec: u32) -> (u64, u32) {
+        match self.mtime_clamp {
+            Some(clamp) if mtime >= clamp => (clamp, 0),
+            _ => (mtime, mtime_nsec),
+        }
+    }
+
+    /// The access/status change/birth time to store (0 if unknown).
+    pub(crate) fn extra_time(&self, time_ns: Option<u64>) -> u64 {
+        if self.drop_extra_times {
+            0
+        } else {
+            time_ns.unwrap_or(0)
+        }
+    }
+}
+
+/// The metadata of a directory created implicitly.
+pub(crate) struct DirMetadata {
+    pub uid: u64,
+    pub gid: u64,
+    pub mode: u64,
+    pub mtime: u64,
+    pub mtime_nsec: u32,
+}
+
+/// How to set the metadata of the directories created implicitly
+/// (parents of added entries which are not added themselves).
+#[derive(Clone, Debug)]
+pub enum ImplicitDirPolicy {
+    /// Use fixed values.
+    Fixed {
+        owner: u64,
+        group: u64,
+        mode: u64,
+        mtime: u64,
+    },
+
+    /// Use the owner, group and mtime of the entry which makes the directory be created.
+    ///
+    /// The mode is the mode of the entry with the search bits set where the read bits are.
+    InheritFromChild,
+
+    /// Use the metadata of the directory on the filesystem.
+    ///
+    /// The path is the directory corresponding to the root of the archive.
+    /// If the directory doesn't exist on the filesystem, the default fixed values are used.
+    FromFilesystem(std::path::PathBuf),
+
+    /// Use the current user and group, with mode 0o755 and mtime 0.
+    CurrentUser,
+}
+
+impl Default for ImplicitDirPolicy {
+    fn default() -> Self {
+        Self::Fixed {
+            owner: 1000,
+            group: 1000,
+            mode: 0o755,
+            mtime: 0,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn current_ids() -> (u64, u64) {
+    unsafe { (libc::getuid() as u64, libc::getgid() as u64) }
+}
+
+#[cfg(windows)]
+fn current_ids() -> (u64, u64) {
+    (1000, 1000)
+}
+
+impl ImplicitDirPolicy {
+    /// The metadata of the directory at `path`, created when adding `child`.
+    pub(crate) fn metadata<E>(&self, path: &crate::Path, child: &E) -> DirMetadata
+    where
+        E: EntryTrait + ?Sized,
+    {
+        match self {
+            Self::Fixed {
+                owner,
+                group,
+                mode,
+                mtime,
+            } => DirMetadata {
+                uid: *owner,
+                gid: *group,
+                mode: *mode,
+                mtime: *mtime,
+                mtime_nsec: 0,
+            },
+            Self::InheritFromChild => {
+                let mode = child.mode() & 0o7777;
+                DirMetadata {
+                    uid: child.uid(),
+                    gid: child.gid(),
+                    mode: mode | ((mode & 0o444) >> 2),
+                    mtime: child.mtime(),
+                    mtime_nsec: child.mtime_nsec(),
+                }
+            }
+            Self::FromFilesystem(root) => match std::fs::metadata(path.to_path(root)) {
+                Ok(attr) if attr.is_dir() => Self::fs_metadata(&attr),
+                _ => Self::default().metadata(path, child),
+            },
+            Self::CurrentUser => {
+                let (uid, gid) = current_ids();
+                DirMetadata {
+                    uid,
+                    gid,
+                    mode: 0o755,
+                    mtime: 0,
+                    mtime_nsec: 0,
+                }
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn fs_metadata(attr: &std::fs::Metadata) -> DirMetadata {
+        DirMetadata {
+            uid: attr.uid() as u64,
+            gid: attr.gid() as u64,
+            mode: attr.mode() as u64,
+            mtime: attr.mtime() as u64,
+            mtime_nsec: attr.mtime_nsec() as u32,
+        }
+    }
+
+    #[cfg(windows)]
+    fn fs_metadata(attr: &std::fs::Metadata) -> DirMetadata {
+        let mtime = attr
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        DirMetadata {
+            uid: 1000,
+            gid: 1000,
+            mode: 0o755,
+            mtime: mtime.as_secs(),
+            mtime_nsec: mtime.subsec_nanos(),
+        }
+    }
+}
@@ -1,21 +1,34 @@
+mod content;
 mod creator;
 mod entry_store_creator;
+mod exclude;
 mod fs_adder;
+mod metadata;
+mod update;
 
+pub use content::{read_content, DEFAULT_SPILL_THRESHOLD};
 pub use creator::SimpleCreator;
 pub use entry_store_creator::EntryStoreCreator;
+pub use exclude::{ExcludeFilter, ExcludeFilterBuilder};
 pub use fs_adder::FsAdder;
+pub(crate) use fs_adder::{detect_kind, fs_mtime, DetectedEntryKind};
+pub use metadata::{ImplicitDirPolicy, MetadataOverride};
+pub use update::carry_over;
 
 #[derive(Clone)]
 pub enum EntryKind {
     Dir,
     File(jbk::Size, jbk::ContentAddress),
     Link(bstr::BString),
+    /// A hard link to a file previously added in the archive.
+    HardLink(crate::PathBuf),
+    /// A device node, a fifo or a socket, with the major and minor device numbers.
+    Special(crate::SpecialKind, u32, u32),
 }
 
 pub trait EntryTrait {
     /// The kind of the entry
-    fn kind(&self) -> jbk::Result<Option<EntryKind>>;
+    fn kind(&self) -> crate::Result<Option<EntryKind>>;
 
     /// Under which name the entry will be stored
     fn path(&self) -> &crate::Path;
@@ -24,6 +37,36 @@ pub trait EntryTrait {
     fn gid(&self) -> u64;
     fn mode(&self) -> u64;
     fn mtime(&self) -> u64;
+
+    /// The sub-second part (in nanoseconds) of the modification time.
+    fn mtime_nsec(&self) -> u32 {
+        0
+    }
+
+    /// The access time (in nanoseconds since epoch), if known.
+    fn atime_ns(&self) -> Option<u64> {
+        None
+    }
+
+    /// The status change time (in nanoseconds since epoch), if known.
+    fn ctime_ns(&self) -> Option<u64> {
+        None
+    }
+
+    /// The creation (birth) time (in nanoseconds since epoch), if known.
+    fn btime_ns(&self) -> Option<u64> {
+        None
+    }
+
+    /// The blake3 hash of the content of a file entry, if computed.
+    fn checksum(&self) -> Option<[u8; 32]> {
+        None
+    }
+
+    /// The extended attributes of the entry.
+    fn xattrs(&self) -> Option<&crate::Xattrs> {
+        None
+    }
 }
 
-pub type Void = jbk::Result<()>;
+pub type Void = crate::Result<()>;
@@ -0,0 +1,255 @@
+use super::entry_store_creator::Lookup;
+use super::{read_content, EntryKind, EntryTrait, SimpleCreator, Void};
+use crate::{Arx, CommonEntry, FullBuilder, Walker};
+use jbk::creator::{ContentAdder, InputReader};
+use jbk::reader::MayMissPack;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Contents up to this size are copied in memory, bigger ones in a temporary file.
+const IN_MEMORY_MAX_SIZE: u64 = 1024 * 1024;
+
+/// A reader on the content `content` (of `size` bytes) of `arx`.
+///
+/// The content is read decompressed and will be compressed again when added: the jubako
+/// creator cannot copy a compressed cluster as is nor reference the packs of another
+/// container (and the existing archive is usually replaced by the new one).
+pub(crate) fn archived_content_reader(
+    arx: &Arx,
+    content: jbk::ContentAddress,
+    size: u64,
+) -> crate::Result<Box<dyn InputReader>> {
+    let bytes = match arx.get_bytes(content)? {
+        MayMissPack::FOUND(bytes) => bytes,
+        MayMissPack::MISSING(pack_info) => {
+            return Err(crate::Error::MissingPack {
+                pack_uuid: pack_info.uuid.to_string(),
+                pack_location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+            })
+        }
+    };
+    let (reader, _size, _checksum) =
+        read_content(&mut bytes.stream(), size, IN_MEMORY_MAX_SIZE, false)?;
+    Ok(reader)
+}
+
+/// A entry of an existing archive, to add in a new archive.
+struct ArchivedEntry {
+    path: crate::PathBuf,
+    kind: EntryKind,
+    uid: u64,
+    gid: u64,
+    mode: u64,
+    mtime: u64,
+    mtime_nsec: u32,
+    atime_ns: Option<u64>,
+    ctime_ns: Option<u64>,
+    btime_ns: Option<u64>,
+    checksum: Option<[u8; 32]>,
+    xattrs: crate::Xattrs,
+}
+
+impl ArchivedEntry {
+    fn new(path: crate::PathBuf, kind: EntryKind, entry: &impl CommonEntry) -> Self {
+        Self {
+            path,
+            kind,
+            uid: entry.owner() as u64,
+            gid: entry.group() as u64,
+            mode: entry.rights() as u64,
+            mtime: entry.mtime(),
+            mtime_nsec: entry.mtime_nsec(),
+            atime_ns: entry.atime_ns(),
+            ctime_ns: entry.ctime_ns(),
+            btime_ns: entry.btime_ns(),
+            checksum: None,
+            xattrs: entry.xattrs().clone(),
+        }
+    }
+}
+
+impl EntryTrait for ArchivedEntry {
+    fn kind(&self) -> crate::Result<Option<EntryKind>> {
+        Ok(Some(self.kind.clone()))
+    }
+    fn path(&self) -> &crate::Path {
+        &self.path
+    }
+    fn uid(&self) -> u64 {
+        self.uid
+    }
+    fn gid(&self) -> u64 {
+        self.gid
+    }
+    fn mode(&self) -> u64 {
+        self.mode
+    }
+    fn mtime(&self) -> u64 {
+        self.mtime
+    }
+    fn mtime_nsec(&self) -> u32 {
+        self.mtime_nsec
+    }
+    fn atime_ns(&self) -> Option<u64> {
+        self.atime_ns
+    }
+    fn ctime_ns(&self) -> Option<u64> {
+        self.ctime_ns
+    }
+    fn btime_ns(&self) -> Option<u64> {
+        self.btime_ns
+    }
+    fn checksum(&self) -> Option<[u8; 32]> {
+        self.checksum
+    }
+    fn xattrs(&self) -> Option<&crate::Xattrs> {
+        Some(&self.xattrs)
+    }
+}
+
+struct CarryOver<'a> {
+    arx: &'a Arx,
+    creator: RefCell<&'a mut SimpleCreator>,
+    removed: &'a [crate::PathBuf],
+    // Path in the new archive of the hard links sets, indexed by
+    // the index of the first file of the set in the old archive.
+    hard_links: RefCell<HashMap<u64, crate::PathBuf>>,
+}
+
+impl CarryOver<'_> {
+    fn is_removed(&self, path: &crate::Path) -> bool {
+        self.removed.iter().any(|r| path.starts_with(r))
+    }
+
+    /// Return true if the old entry at `path` must be added in the new archive.
+    fn must_add(&self, path: &crate::Path) -> bool {
+        !self.is_removed(path) && matches!(self.creator.borrow().lookup(path), Lookup::Missing)
+    }
+
+    fn add(&self, entry: ArchivedEntry) -> Void {
+        self.creator.borrow_mut().add_entry(&entry)
+    }
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for CarryOver<'_> {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        current_path.push(String::from_utf8(dir.path().clone())?);
+        if self.is_removed(current_path) {
+            return Ok(false);
+        }
+        match self.creator.borrow().lookup(current_path) {
+            // Directory is merged with the new one.
+            Lookup::Dir => return Ok(true),
+            // Directory is replaced by something else.
+            Lookup::Other => return Ok(false),
+            Lookup::Missing => {}
+        }
+        self.add(ArchivedEntry::new(
+            current_path.clone(),
+            EntryKind::Dir,
+            dir,
+        ))?;
+        Ok(true)
+    }
+
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
+        current_path.pop();
+        Ok(())
+    }
+
+    fn on_file(&self, current_path: &mut crate::PathBuf, file: &crate::FileEntry) -> Void {
+        let path = current_path.join(String::from_utf8(file.path().clone())?);
+        if !self.must_add(&path) {
+            return Ok(());
+        }
+        // All the entries of a hard links set are keyed by the index of the first one.
+        let set_idx = file.hard_link().unwrap_or(file.idx()).into_u64();
+        let target = if file.nlink() > 1 {
+            self.hard_links.borrow().get(&set_idx).cloned()
+        } else {
+            None
+        };
+        let mut entry = match target {
+            Some(target) => ArchivedEntry::new(path.clone(), EntryKind::HardLink(target), file),
+            None => {
+                let reader =
+                    archived_content_reader(self.arx, file.content(), file.size().into_u64())?;
+                let content_address = self
+                    .creator
+                    .borrow_mut()
+                    .adder()
+                    .add_content(reader, jbk::creator::CompHint::Detect)?;
+                if file.nlink() > 1 {
+                    self.hard_links.borrow_mut().insert(set_idx, path.clone());
+                }
+                ArchivedEntry::new(
+                    path.clone(),
+                    EntryKind::File(file.size(), content_address),
+                    file,
+                )
+            }
+        };
+        entry.checksum = file.checksum().copied();
+        self.add(entry)
+    }
+
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &crate::Link) -> Void {
+        let path = current_path.join(String::from_utf8(link.path().clone())?);
+        if !self.must_add(&path) {
+            return Ok(());
+        }
+        self.add(ArchivedEntry::new(
+            path,
+            EntryKind::Link(link.target().clone().into()),
+            link,
+        ))
+    }
+
+    fn on_special(&self, current_path: &mut crate::PathBuf, special: &crate::Special) -> Void {
+        let path = current_path.join(String::from_utf8(special.path().clone())?);
+        if !self.must_add(&path) {
+            return Ok(());
+        }
+        self.add(ArchivedEntry::new(
+            path,
+            EntryKind::Special(special.kind(), special.major(), special.minor()),
+            special,
+        ))
+    }
+}
+
+/// Add the entries of `arx` in `creator`.
+///
+/// Entries already added in `creator` (replaced entries) and entries in (or under) the
+/// paths in `removed` are not added. Directories existing in both are merged.
+/// The content of the added files is copied from `arx` (decompressed and compressed again,
+/// see `archived_content_reader`).
+///
+/// This is used to update an archive: the new entries are added first, then the
+/// entries of the old archive are carried over.
+pub fn carry_over(arx: &Arx, creator: &mut SimpleCreator, removed: &[crate::PathBuf]) -> Void {
+    let operator = CarryOver {
+        arx,
+        creator: RefCell::new(creator),
+        removed,
+        hard_links: Default::default(),
+    };
+    let mut walker = Walker::new(arx, Default::default());
+    walker.run(&operator)
+}
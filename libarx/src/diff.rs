@@ -0,0 +1,552 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use bstr::ByteVec;
+
+use crate::create::{detect_kind, fs_mtime, DetectedEntryKind};
+use crate::{Arx, CommonEntry, FullBuilder, SpecialKind, Walker};
+use jbk::reader::MayMissPack;
+
+/// Size of the buffers used to compare contents.
+const COMPARE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A difference in the metadata or the content of an entry present on both sides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Mode {
+        old: u64,
+        new: u64,
+    },
+    Owner {
+        old: u64,
+        new: u64,
+    },
+    Group {
+        old: u64,
+        new: u64,
+    },
+    /// Modification times, in (seconds, nanoseconds) since epoch.
+    Mtime {
+        old: (u64, u32),
+        new: (u64, u32),
+    },
+    Size {
+        old: u64,
+        new: u64,
+    },
+    LinkTarget {
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+    /// Major and minor numbers of a device.
+    Device {
+        old: (u32, u32),
+        new: (u32, u32),
+    },
+    /// Files have the same size but a different content.
+    Content,
+}
+
+fn fmt_time(f: &mut std::fmt::Formatter, (secs, nsecs): (u64, u32)) -> std::fmt::Result {
+    write!(f, "{secs}.{nsecs:09}")
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Mode { old, new } => write!(f, "mode {old:o} -> {new:o}"),
+            Self::Owner { old, new } => write!(f, "owner {old} -> {new}"),
+            Self::Group { old, new } => write!(f, "group {old} -> {new}"),
+            Self::Mtime { old, new } => {
+                write!(f, "mtime ")?;
+                fmt_time(f, *old)?;
+                write!(f, " -> ")?;
+                fmt_time(f, *new)
+            }
+            Self::Size { old, new } => write!(f, "size {old} -> {new}"),
+            Self::LinkTarget { old, new } => write!(
+                f,
+                "link target {} -> {}",
+                String::from_utf8_lossy(old),
+                String::from_utf8_lossy(new)
+            ),
+            Self::Device { old, new } => {
+                write!(f, "device {}:{} -> {}:{}", old.0, old.1, new.0, new.1)
+            }
+            Self::Content => write!(f, "content"),
+        }
+    }
+}
+
+/// A difference between the two sides of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The entry is only in the new side.
+    Added { path: crate::PathBuf },
+    /// The entry is only in the old side.
+    Removed { path: crate::PathBuf },
+    /// The entry is on both sides but with a different type (file, dir, link, ...).
+    TypeChanged {
+        path: crate::PathBuf,
+        old: &'static str,
+        new: &'static str,
+    },
+    /// The entry is on both sides with the same type but with different metadata or content.
+    Modified {
+        path: crate::PathBuf,
+        changes: Vec<Change>,
+    },
+}
+
+impl Difference {
+    pub fn path(&self) -> &crate::Path {
+        match self {
+            Self::Added { path }
+            | Self::Removed { path }
+            | Self::TypeChanged { path, .. }
+            | Self::Modified { path, .. } => path,
+        }
+    }
+}
+
+impl std::fmt::Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Added { path } => write!(f, "+ {path}"),
+            Self::Removed { path } => write!(f, "- {path}"),
+            Self::TypeChanged { path, old, new } => write!(f, "T {path}: {old} -> {new}"),
+            Self::Modified { path, changes } => {
+                write!(f, "M {path}:")?;
+                for (i, change) in changes.iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{sep}{change}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What to compare.
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Do not compare mode, owner, group and modification time.
+    pub ignore_metadata: bool,
+
+    /// Do not compare modification time.
+    pub ignore_mtime: bool,
+}
+
+enum Content {
+    Archived {
+        address: jbk::ContentAddress,
+        checksum: Option<[u8; 32]>,
+    },
+    OnDisk(std::path::PathBuf),
+}
+
+enum NodeKind {
+    Dir,
+    File { size: u64, content: Content },
+    Link(Vec<u8>),
+    Special(SpecialKind, u32, u32),
+    Other,
+}
+
+impl NodeKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Dir => "dir",
+            Self::File { .. } => "file",
+            Self::Link(_) => "link",
+            Self::Special(SpecialKind::Fifo, _, _) => "fifo",
+            Self::Special(SpecialKind::Socket, _, _) => "socket",
+            Self::Special(SpecialKind::CharDevice, _, _) => "char device",
+            Self::Special(SpecialKind::BlockDevice, _, _) => "block device",
+            Self::Other => "other",
+        }
+    }
+}
+
+struct Node {
+    kind: NodeKind,
+    /// Mode, owner and group, None if the filesystem doesn't have them.
+    mode: Option<u64>,
+    uid: Option<u64>,
+    gid: Option<u64>,
+    mtime: (u64, u32),
+}
+
+impl Node {
+    fn from_entry(kind: NodeKind, entry: &impl CommonEntry) -> Self {
+        Self {
+            kind,
+            mode: Some(entry.rights() as u64 & 0o7777),
+            uid: Some(entry.owner() as u64),
+            gid: Some(entry.group() as u64),
+            mtime: (entry.mtime(), entry.mtime_nsec()),
+        }
+    }
+}
+
+/// One side of a diff: the entries (indexed by path) and where to read their content.
+struct Side<'a> {
+    arx: Option<&'a Arx>,
+    nodes: BTreeMap<crate::PathBuf, Node>,
+}
+
+/// A content opened for reading.
+enum Opened {
+    Bytes(jbk::reader::ByteRegion),
+    File(std::fs::File),
+}
+
+impl Opened {
+    fn reader(&mut self) -> Box<dyn Read + '_> {
+        match self {
+            Self::Bytes(bytes) => Box::new(bytes.stream()),
+            Self::File(file) => Box::new(file),
+        }
+    }
+}
+
+impl Side<'_> {
+    fn open(&self, content: &Content) -> crate::Result<Opened> {
+        match content {
+            Content::OnDisk(path) => Ok(Opened::File(std::fs::File::open(path)?)),
+            Content::Archived { address, .. } => {
+                let arx = self.arx.expect("Archived content comes from an archive");
+                match arx.get_bytes(*address)? {
+                    MayMissPack::FOUND(bytes) => Ok(Opened::Bytes(bytes)),
+                    MayMissPack::MISSING(pack_info) => Err(crate::Error::MissingPack {
+                        pack_uuid: pack_info.uuid.to_string(),
+                        pack_location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// Read from `reader` until `buf` is full or the end of the stream is reached.
+fn fill(reader: &mut dyn Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn same_bytes(a: &mut dyn Read, b: &mut dyn Read) -> std::io::Result<bool> {
+    let mut buf_a = vec![0; COMPARE_BUFFER_SIZE];
+    let mut buf_b = vec![0; COMPARE_BUFFER_SIZE];
+    loop {
+        let read_a = fill(a, &mut buf_a)?;
+        let read_b = fill(b, &mut buf_b)?;
+        if buf_a[..read_a] != buf_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+struct Differ<'a> {
+    old: Side<'a>,
+    new: Side<'a>,
+    options: &'a DiffOptions,
+}
+
+impl Differ<'_> {
+    /// Compare the content of two files of the same size.
+    fn same_content(&self, old: &Content, new: &Content) -> crate::Result<bool> {
+        if let (
+            Content::Archived {
+                checksum: Some(old_checksum),
+                ..
+            },
+            Content::Archived {
+                checksum: Some(new_checksum),
+                ..
+            },
+        ) = (old, new)
+        {
+            return Ok(old_checksum == new_checksum);
+        }
+        let mut old = self.old.open(old)?;
+        let mut new = self.new.open(new)?;
+        Ok(same_bytes(&mut old.reader(), &mut new.reader())?)
+    }
+
+    fn compare(&self, old: &Node, new: &Node) -> crate::Result<Vec<Change>> {
+        let mut changes = vec![];
+        if !self.options.ignore_metadata {
+            if let (Some(old), Some(new)) = (old.mode, new.mode) {
+                if old != new {
+                    changes.push(Change::Mode { old, new });
+                }
+            }
+            if let (Some(old), Some(new)) = (old.uid, new.uid) {
+                if old != new {
+                    changes.push(Change::Owner { old, new });
+                }
+            }
+            if let (Some(old), Some(new)) = (old.gid, new.gid) {
+                if old != new {
+                    changes.push(Change::Group { old, new });
+                }
+            }
+            if !self.options.ignore_mtime && old.mtime != new.mtime {
+                changes.push(Change::Mtime {
+                    old: old.mtime,
+                    new: new.mtime,
+                });
+            }
+        }
+        match (&old.kind, &new.kind) {
+            (
+                NodeKind::File {
+                    size: old_size,
+                    content: old_content,
+                },
+                NodeKind::File {
+                    size: new_size,
+                    content: new_content,
+                },
+            ) => {
+                if old_size != new_size {
+                    changes.push(Change::Size {
+                        old: *old_size,
+                        new: *new_size,
+                    });
+                } else if !self.same_content(old_content, new_content)? {
+                    changes.push(Change::Content);
+                }
+            }
+            (NodeKind::Link(old_target), NodeKind::Link(new_target)) => {
+                if old_target != new_target {
+                    changes.push(Change::LinkTarget {
+                        old: old_target.clone(),
+                        new: new_target.clone(),
+                    });
+                }
+            }
+            (
+                NodeKind::Special(_, old_major, old_minor),
+                NodeKind::Special(_, new_major, new_minor),
+            ) => {
+                if (old_major, old_minor) != (new_major, new_minor) {
+                    changes.push(Change::Device {
+                        old: (*old_major, *old_minor),
+                        new: (*new_major, *new_minor),
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(changes)
+    }
+
+    fn run(&self) -> crate::Result<Vec<Difference>> {
+        let paths: BTreeSet<&crate::PathBuf> =
+            self.old.nodes.keys().chain(self.new.nodes.keys()).collect();
+        let mut differences = vec![];
+        for path in paths {
+            let path = path.clone();
+            match (self.old.nodes.get(&path), self.new.nodes.get(&path)) {
+                (Some(_), None) => differences.push(Difference::Removed { path }),
+                (None, Some(_)) => differences.push(Difference::Added { path }),
+                (Some(old), Some(new)) => {
+                    if old.kind.name() != new.kind.name() {
+                        differences.push(Difference::TypeChanged {
+                            path,
+                            old: old.kind.name(),
+                            new: new.kind.name(),
+                        });
+                        continue;
+                    }
+                    let changes = self.compare(old, new)?;
+                    if !changes.is_empty() {
+                        differences.push(Difference::Modified { path, changes });
+                    }
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+        Ok(differences)
+    }
+}
+
+/// Collect the entries of an archive.
+#[derive(Default)]
+struct Collector {
+    nodes: RefCell<BTreeMap<crate::PathBuf, Node>>,
+}
+
+impl Collector {
+    fn insert(&self, current_path: &crate::Path, entry: &impl CommonEntry, kind: NodeKind) {
+        let path = current_path.join(String::from_utf8_lossy(entry.path()).as_ref());
+        self.nodes
+            .borrow_mut()
+            .insert(path, Node::from_entry(kind, entry));
+    }
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for Collector {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        self.insert(current_path, dir, NodeKind::Dir);
+        current_path.push(String::from_utf8_lossy(dir.path()).as_ref());
+        Ok(true)
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(
+        &self,
+        current_path: &mut crate::PathBuf,
+        entry: &crate::FileEntry,
+    ) -> crate::Result<()> {
+        let kind = NodeKind::File {
+            size: entry.size().into_u64(),
+            content: Content::Archived {
+                address: entry.content(),
+                checksum: entry.checksum().copied(),
+            },
+        };
+        self.insert(current_path, entry, kind);
+        Ok(())
+    }
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &crate::Link) -> crate::Result<()> {
+        self.insert(current_path, link, NodeKind::Link(link.target().clone()));
+        Ok(())
+    }
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &crate::Special,
+    ) -> crate::Result<()> {
+        let kind = NodeKind::Special(special.kind(), special.major(), special.minor());
+        self.insert(current_path, special, kind);
+        Ok(())
+    }
+}
+
+fn archive_side(arx: &Arx) -> crate::Result<Side<'_>> {
+    let collector = Collector::default();
+    let mut walker = Walker::new(arx, Default::default());
+    walker.run(&collector)?;
+    Ok(Side {
+        arx: Some(arx),
+        nodes: collector.nodes.into_inner(),
+    })
+}
+
+#[cfg(unix)]
+fn fs_owner(attr: &std::fs::Metadata) -> (Option<u64>, Option<u64>, Option<u64>) {
+    (
+        Some(attr.uid() as u64),
+        Some(attr.gid() as u64),
+        Some(attr.mode() as u64 & 0o7777),
+    )
+}
+
+/// Windows has no unix owner, group and mode to compare with.
+#[cfg(windows)]
+fn fs_owner(_attr: &std::fs::Metadata) -> (Option<u64>, Option<u64>, Option<u64>) {
+    (None, None, None)
+}
+
+fn dir_side(dir: &Path) -> crate::Result<Side<'static>> {
+    let mut nodes = BTreeMap::new();
+    for entry in walkdir::WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let path = crate::PathBuf::from_path(entry.path().strip_prefix(dir).unwrap())?;
+        let (kind, attr) = detect_kind(entry.path().to_path_buf(), false)?;
+        let kind = match kind {
+            DetectedEntryKind::Dir => NodeKind::Dir,
+            DetectedEntryKind::File(size, path) => NodeKind::File {
+                size,
+                content: Content::OnDisk(path),
+            },
+            DetectedEntryKind::Link(path) => {
+                // Compare the raw bytes, as they are stored when the archive is created.
+                let target = Vec::from_path_buf(std::fs::read_link(path)?).map_err(|target| {
+                    crate::Error::InvalidPath(format!("{} is not utf-8", target.display()))
+                })?;
+                NodeKind::Link(target)
+            }
+            DetectedEntryKind::Special(kind, major, minor) => NodeKind::Special(kind, major, minor),
+            DetectedEntryKind::Other => NodeKind::Other,
+        };
+        let (uid, gid, mode) = fs_owner(&attr);
+        nodes.insert(
+            path,
+            Node {
+                kind,
+                mode,
+                uid,
+                gid,
+                mtime: fs_mtime(&attr),
+            },
+        );
+    }
+    Ok(Side { arx: None, nodes })
+}
+
+/// Compare two archives.
+///
+/// Differences are returned sorted by path. Contents are compared only if the files have
+/// the same size, using the stored checksums if both have one, else reading the contents.
+pub fn diff_archives(
+    old: &Arx,
+    new: &Arx,
+    options: &DiffOptions,
+) -> crate::Result<Vec<Difference>> {
+    Differ {
+        old: archive_side(old)?,
+        new: archive_side(new)?,
+        options,
+    }
+    .run()
+}
+
+/// Compare an archive with a directory (as if the directory was the root of the archive).
+///
+/// Symbolic links in the directory are not followed.
+pub fn diff_archive_dir(
+    arx: &Arx,
+    dir: &Path,
+    options: &DiffOptions,
+) -> crate::Result<Vec<Difference>> {
+    Differ {
+        old: archive_side(arx)?,
+        new: dir_side(dir)?,
+        options,
+    }
+    .run()
+}
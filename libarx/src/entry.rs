@@ -1,4 +1,4 @@
-use crate::common::{AllProperties, Builder};
+use crate::common::{AllProperties, Builder, SpecialKind, Xattrs};
 use jbk::reader::builder::PropertyBuilderTrait;
 use jbk::reader::ByteSlice;
 
@@ -9,8 +9,13 @@ pub struct CommonPart {
     parent: Option<jbk::EntryIdx>,
     owner: u32,
     group: u32,
-    rights: u8,
+    rights: u16,
     mtime: u64,
+    mtime_nsec: u32,
+    atime_ns: Option<u64>,
+    ctime_ns: Option<u64>,
+    btime_ns: Option<u64>,
+    xattrs: Xattrs,
 }
 
 pub trait CommonEntry {
@@ -30,12 +35,33 @@ pub trait CommonEntry {
     fn group(&self) -> u32 {
         self.common().group
     }
-    fn rights(&self) -> u8 {
+    /// The permission bits (including setuid, setgid and sticky bits) of the entry.
+    fn rights(&self) -> u16 {
         self.common().rights
     }
     fn mtime(&self) -> u64 {
         self.common().mtime
     }
+    /// The sub-second part (in nanoseconds) of the modification time.
+    fn mtime_nsec(&self) -> u32 {
+        self.common().mtime_nsec
+    }
+    /// The access time (in nanoseconds since epoch), if known.
+    fn atime_ns(&self) -> Option<u64> {
+        self.common().atime_ns
+    }
+    /// The status change time (in nanoseconds since epoch), if known.
+    fn ctime_ns(&self) -> Option<u64> {
+        self.common().ctime_ns
+    }
+    /// The creation (birth) time (in nanoseconds since epoch), if known.
+    fn btime_ns(&self) -> Option<u64> {
+        self.common().btime_ns
+    }
+    /// The extended attributes of the entry.
+    fn xattrs(&self) -> &Xattrs {
+        &self.common().xattrs
+    }
 }
 
 #[derive(Clone)]
@@ -43,6 +69,9 @@ pub struct FileEntry {
     common: CommonPart,
     content: jbk::ContentAddress,
     size: jbk::Size,
+    hard_link: Option<jbk::EntryIdx>,
+    nlink: u64,
+    checksum: Option<[u8; 32]>,
 }
 
 impl CommonEntry for FileEntry {
@@ -58,6 +87,20 @@ impl FileEntry {
     pub fn size(&self) -> jbk::Size {
         self.size
     }
+    /// The index of the file entry this entry is a hard link to.
+    ///
+    /// This is `None` for the first (or only) entry of a set of hard links.
+    pub fn hard_link(&self) -> Option<jbk::EntryIdx> {
+        self.hard_link
+    }
+    /// The number of entries sharing the same content (hard links).
+    pub fn nlink(&self) -> u64 {
+        self.nlink
+    }
+    /// The blake3 hash of the content, if it has been computed at creation.
+    pub fn checksum(&self) -> Option<&[u8; 32]> {
+        self.checksum.as_ref()
+    }
 }
 
 #[derive(Clone)]
@@ -96,6 +139,34 @@ impl Dir {
     }
 }
 
+#[derive(Clone)]
+pub struct Special {
+    common: CommonPart,
+    kind: SpecialKind,
+    major: u32,
+    minor: u32,
+}
+
+impl CommonEntry for Special {
+    fn common(&self) -> &CommonPart {
+        &self.common
+    }
+}
+
+impl Special {
+    pub fn kind(&self) -> SpecialKind {
+        self.kind
+    }
+    /// The major device number. Only meaningful for char and block devices.
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+    /// The minor device number. Only meaningful for char and block devices.
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+}
+
 mod private {
     use super::*;
     pub struct CommonBuilder {
@@ -105,6 +176,25 @@ mod private {
         group_property: jbk::reader::builder::IntProperty,
         rights_property: jbk::reader::builder::IntProperty,
         mtime_property: jbk::reader::builder::IntProperty,
+        mtime_nsec_property: Option<jbk::reader::builder::IntProperty>,
+        atime_property: Option<jbk::reader::builder::IntProperty>,
+        ctime_property: Option<jbk::reader::builder::IntProperty>,
+        btime_property: Option<jbk::reader::builder::IntProperty>,
+        xattrs_property: Option<jbk::reader::builder::ArrayProperty>,
+    }
+
+    /// Read a optional time property, 0 meaning unknown time.
+    fn read_time(
+        property: &Option<jbk::reader::builder::IntProperty>,
+        reader: &ByteSlice,
+    ) -> jbk::Result<Option<u64>> {
+        Ok(match property {
+            Some(p) => match p.create(reader)? {
+                0 => None,
+                t => Some(t),
+            },
+            None => None,
+        })
     }
 
     impl CommonBuilder {
@@ -116,6 +206,11 @@ mod private {
                 group_property: properties.group_property.clone(),
                 rights_property: properties.rigths_property.clone(),
                 mtime_property: properties.mtime_property.clone(),
+                mtime_nsec_property: properties.mtime_nsec_property.clone(),
+                atime_property: properties.atime_property.clone(),
+                ctime_property: properties.ctime_property.clone(),
+                btime_property: properties.btime_property.clone(),
+                xattrs_property: properties.xattrs_property.clone(),
             }
         }
 
@@ -129,14 +224,28 @@ mod private {
             } else {
                 Some((parent as u32 - 1).into())
             };
+            let xattrs = match &self.xattrs_property {
+                Some(xattrs_property) => {
+                    let xattrs_prop = xattrs_property.create(reader)?;
+                    let mut xattrs = vec![];
+                    xattrs_prop.resolve_to_vec(&mut xattrs)?;
+                    Xattrs::from_bytes(&xattrs)?
+                }
+                None => Xattrs::new(),
+            };
             Ok(CommonPart {
                 idx,
                 path,
                 parent,
                 owner: self.owner_property.create(reader)? as u32,
                 group: self.group_property.create(reader)? as u32,
-                rights: self.rights_property.create(reader)? as u8,
+                rights: (self.rights_property.create(reader)? & 0o7777) as u16,
                 mtime: self.mtime_property.create(reader)?,
+                mtime_nsec: read_time(&self.mtime_nsec_property, reader)?.unwrap_or(0) as u32,
+                atime_ns: read_time(&self.atime_property, reader)?,
+                ctime_ns: read_time(&self.ctime_property, reader)?,
+                btime_ns: read_time(&self.btime_property, reader)?,
+                xattrs,
             })
         }
     }
@@ -145,6 +254,9 @@ mod private {
         common: CommonBuilder,
         content_address_property: jbk::reader::builder::ContentProperty,
         size_property: jbk::reader::builder::IntProperty,
+        hard_link_property: Option<jbk::reader::builder::IntProperty>,
+        nlink_property: Option<jbk::reader::builder::IntProperty>,
+        checksum_property: Option<jbk::reader::builder::ArrayProperty>,
     }
 
     impl Builder for FileBuilder {
@@ -155,14 +267,41 @@ mod private {
                 common: CommonBuilder::new(properties),
                 content_address_property: properties.file_content_address_property,
                 size_property: properties.file_size_property.clone(),
+                hard_link_property: properties.file_hard_link_property.clone(),
+                nlink_property: properties.file_nlink_property.clone(),
+                checksum_property: properties.file_checksum_property.clone(),
             }
         }
 
         fn create_entry(&self, idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+            let hard_link = match &self.hard_link_property {
+                Some(p) => match p.create(reader)? {
+                    0 => None,
+                    v => Some((v as u32 - 1).into()),
+                },
+                None => None,
+            };
+            let nlink = match &self.nlink_property {
+                Some(p) => p.create(reader)?,
+                None => 1,
+            };
+            let checksum = match &self.checksum_property {
+                Some(p) => {
+                    let checksum_prop = p.create(reader)?;
+                    let mut checksum = vec![];
+                    checksum_prop.resolve_to_vec(&mut checksum)?;
+                    // Checksum is empty if it has not been computed.
+                    checksum.try_into().ok()
+                }
+                None => None,
+            };
             Ok(FileEntry {
                 common: self.common.create_entry(idx, reader)?,
                 content: self.content_address_property.create(reader)?,
                 size: self.size_property.create(reader)?.into(),
+                hard_link,
+                nlink,
+                checksum,
             })
         }
     }
@@ -220,12 +359,45 @@ mod private {
             })
         }
     }
+
+    pub struct SpecialBuilder {
+        common: CommonBuilder,
+        kind_property: Option<jbk::reader::builder::IntProperty>,
+        major_property: Option<jbk::reader::builder::IntProperty>,
+        minor_property: Option<jbk::reader::builder::IntProperty>,
+    }
+
+    impl Builder for SpecialBuilder {
+        type Entry = Special;
+
+        fn new(properties: &AllProperties) -> Self {
+            Self {
+                common: CommonBuilder::new(properties),
+                kind_property: properties.special_kind_property.clone(),
+                major_property: properties.special_major_property.clone(),
+                minor_property: properties.special_minor_property.clone(),
+            }
+        }
+
+        fn create_entry(&self, idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
+            let common = self.common.create_entry(idx, reader)?;
+            // We are called only for special entries, so the properties must exist.
+            let kind = self.kind_property.as_ref().unwrap().create(reader)?;
+            Ok(Special {
+                common,
+                kind: kind.try_into()?,
+                major: self.major_property.as_ref().unwrap().create(reader)? as u32,
+                minor: self.minor_property.as_ref().unwrap().create(reader)? as u32,
+            })
+        }
+    }
 } // private mode
 
 pub type FullBuilder = (
     private::FileBuilder,
     private::LinkBuilder,
     private::DirBuilder,
+    private::SpecialBuilder,
 );
 
-pub type FullEntry = super::Entry<(FileEntry, Link, Dir)>;
+pub type FullEntry = super::Entry<(FileEntry, Link, Dir, Special)>;
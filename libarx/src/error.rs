@@ -0,0 +1,109 @@
+/// The error type of arx operations.
+#[derive(Debug)]
+pub enum Error {
+    /// There is no entry at the given path.
+    NotFound(crate::PathBuf),
+
+    /// The entry at the given path (or one of its parents) is not a directory.
+    NotADirectory(crate::PathBuf),
+
+    /// An entry already exists at the given path.
+    AlreadyExists(crate::PathBuf),
+
+    /// The path cannot be used as a path in an arx archive.
+    InvalidPath(String),
+
+    /// The pack containing a content is missing.
+    MissingPack {
+        pack_uuid: String,
+        pack_location: String,
+    },
+
+    /// An io error on the filesystem.
+    Io(std::io::Error),
+
+    /// The archive is invalid (or jubako failed to read or write it).
+    Format(jbk::Error),
+
+    /// Some entries failed to be extracted.
+    ///
+    /// Contains the path of each failing entry with its error.
+    Extract(Vec<(std::path::PathBuf, Error)>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "Cannot found entry {path}"),
+            Self::NotADirectory(path) => write!(f, "{path} is not a directory"),
+            Self::AlreadyExists(path) => write!(f, "An entry already exists at {path}"),
+            Self::InvalidPath(msg) => write!(f, "Invalid path: {msg}"),
+            Self::MissingPack {
+                pack_uuid,
+                pack_location,
+            } => write!(
+                f,
+                "Missing pack {pack_uuid}. Declared location is {pack_location}"
+            ),
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Format(e) => write!(f, "{e}"),
+            Self::Extract(errors) => {
+                write!(f, "{} entries failed to be extracted:", errors.len())?;
+                for (path, e) in errors {
+                    write!(f, "\n  {}: {e}", path.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Format(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<jbk::Error> for Error {
+    fn from(e: jbk::Error) -> Self {
+        match e.error {
+            jbk::ErrorKind::Io(e) => Self::Io(e),
+            _ => Self::Format(e),
+        }
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Error {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Self::Format(e.into())
+    }
+}
+
+impl From<crate::FromPathError> for Error {
+    fn from(e: crate::FromPathError) -> Self {
+        Self::InvalidPath(e.to_string())
+    }
+}
+
+/// Allow arx errors to be returned from jubako traits (builders, ...).
+impl From<Error> for jbk::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e.into(),
+            Error::Format(e) => e,
+            e => e.to_string().into(),
+        }
+    }
+}
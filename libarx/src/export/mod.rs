@@ -0,0 +1,34 @@
+mod tar;
+mod zip;
+
+pub use self::tar::{export_tar, TarExporter};
+pub use self::zip::{export_zip, ZipExporter};
+
+use crate::{Arx, FullBuilder, Walker};
+
+/// Run `operator` on the entries of `arx`, or on the entries under the directory `root`.
+fn walk(
+    arx: &Arx,
+    root: Option<&crate::Path>,
+    operator: &dyn crate::walk::Operator<crate::PathBuf, FullBuilder>,
+) -> crate::Result<()> {
+    let mut walker = Walker::new(arx, Default::default());
+    match root {
+        None => walker.run(operator),
+        Some(root) => match arx.get_entry::<FullBuilder>(root)? {
+            crate::Entry::Dir(range, _) => walker.run_from_range(operator, &range),
+            _ => Err(crate::Error::NotADirectory(root.to_owned())),
+        },
+    }
+}
+
+/// The content of the file `entry`.
+fn file_content(arx: &Arx, entry: &crate::FileEntry) -> crate::Result<jbk::reader::ByteRegion> {
+    match arx.get_bytes(entry.content())? {
+        jbk::reader::MayMissPack::FOUND(bytes) => Ok(bytes),
+        jbk::reader::MayMissPack::MISSING(pack_info) => Err(crate::Error::MissingPack {
+            pack_uuid: pack_info.uuid.to_string(),
+            pack_location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+        }),
+    }
+}
@@ -0,0 +1,243 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use crate::{Arx, CommonEntry, FullBuilder, SpecialKind};
+use ::tar::{EntryType, Header};
+
+/// Biggest value of the numeric fields of a ustar header (7 octal digits).
+const USTAR_ID_MAX: u64 = 0o7777777;
+
+/// Biggest size of a ustar header (11 octal digits).
+const USTAR_SIZE_MAX: u64 = 0o77777777777;
+
+/// Append a PAX record ("<len> <key>=<value>\n") to `records`.
+fn add_pax_record(records: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    // The length includes the length field itself.
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + 1;
+    while len != rest + len.to_string().len() {
+        len = rest + len.to_string().len();
+    }
+    records.extend_from_slice(format!("{len} ").as_bytes());
+    records.extend_from_slice(key);
+    records.push(b'=');
+    records.extend_from_slice(value);
+    records.push(b'\n');
+}
+
+/// Set a name field of a header. Return false if the value is too long (and is truncated).
+fn set_name_field(field: &mut [u8; 100], value: &[u8]) -> bool {
+    let len = value.len().min(field.len());
+    field[..len].copy_from_slice(&value[..len]);
+    value.len() <= field.len()
+}
+
+/// Description of a entry to write in the tar.
+struct TarMember<'a> {
+    path: String,
+    entry_type: EntryType,
+    size: u64,
+    link_name: Option<&'a [u8]>,
+    device: Option<(u32, u32)>,
+}
+
+/// Write the entries of an archive as a PAX tar.
+///
+/// Metadata which doesn't fit in the ustar header (long paths and link targets,
+/// big ids and sizes, sub-second mtimes, extended attributes) is stored in PAX headers.
+/// Sockets cannot be stored in a tar and are skipped.
+pub struct TarExporter<'a, W: Write> {
+    arx: &'a Arx,
+    builder: RefCell<::tar::Builder<W>>,
+    // Path in the tar of the hard links sets, indexed by
+    // the index of the first file of the set in the archive.
+    hard_links: RefCell<HashMap<u64, crate::PathBuf>>,
+}
+
+impl<'a, W: Write> TarExporter<'a, W> {
+    pub fn new(arx: &'a Arx, writer: W) -> Self {
+        Self {
+            arx,
+            builder: RefCell::new(::tar::Builder::new(writer)),
+            hard_links: Default::default(),
+        }
+    }
+
+    /// Write the end of the tar and return the writer.
+    pub fn finish(self) -> crate::Result<W> {
+        Ok(self.builder.into_inner().into_inner()?)
+    }
+
+    fn append(
+        &self,
+        member: TarMember,
+        entry: &impl CommonEntry,
+        data: impl Read,
+    ) -> crate::Result<()> {
+        let mut header = Header::new_ustar();
+        let mut records = vec![];
+        header.set_entry_type(member.entry_type);
+        if !set_name_field(&mut header.as_old_mut().name, member.path.as_bytes()) {
+            add_pax_record(&mut records, b"path", member.path.as_bytes());
+        }
+        if let Some(link_name) = member.link_name {
+            if !set_name_field(&mut header.as_old_mut().linkname, link_name) {
+                add_pax_record(&mut records, b"linkpath", link_name);
+            }
+        }
+        header.set_mode(entry.rights() as u32);
+        let (uid, gid) = (entry.owner() as u64, entry.group() as u64);
+        header.set_uid(uid);
+        header.set_gid(gid);
+        if uid > USTAR_ID_MAX {
+            add_pax_record(&mut records, b"uid", uid.to_string().as_bytes());
+        }
+        if gid > USTAR_ID_MAX {
+            add_pax_record(&mut records, b"gid", gid.to_string().as_bytes());
+        }
+        header.set_mtime(entry.mtime());
+        if entry.mtime_nsec() != 0 {
+            let mtime = format!("{}.{:09}", entry.mtime(), entry.mtime_nsec());
+            add_pax_record(&mut records, b"mtime", mtime.as_bytes());
+        }
+        header.set_size(member.size);
+        if member.size > USTAR_SIZE_MAX {
+            add_pax_record(&mut records, b"size", member.size.to_string().as_bytes());
+        }
+        if let Some((major, minor)) = member.device {
+            header.set_device_major(major)?;
+            header.set_device_minor(minor)?;
+        }
+        for (name, value) in entry.xattrs().iter() {
+            let mut key = b"SCHILY.xattr.".to_vec();
+            key.extend_from_slice(name);
+            add_pax_record(&mut records, &key, value);
+        }
+        header.set_cksum();
+
+        let mut builder = self.builder.borrow_mut();
+        if !records.is_empty() {
+            let mut pax_header = Header::new_ustar();
+            pax_header.set_entry_type(EntryType::XHeader);
+            let name = format!("PaxHeaders/{}", member.path.trim_end_matches('/'));
+            set_name_field(&mut pax_header.as_old_mut().name, name.as_bytes());
+            pax_header.set_mode(0o644);
+            pax_header.set_mtime(entry.mtime());
+            pax_header.set_size(records.len() as u64);
+            pax_header.set_cksum();
+            builder.append(&pax_header, records.as_slice())?;
+        }
+        builder.append(&header, data)?;
+        Ok(())
+    }
+}
+
+impl<W: Write> crate::walk::Operator<crate::PathBuf, FullBuilder> for TarExporter<'_, W> {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        current_path.push(String::from_utf8(dir.path().clone())?);
+        let member = TarMember {
+            path: format!("{current_path}/"),
+            entry_type: EntryType::Directory,
+            size: 0,
+            link_name: None,
+            device: None,
+        };
+        self.append(member, dir, std::io::empty())?;
+        Ok(true)
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(
+        &self,
+        current_path: &mut crate::PathBuf,
+        file: &crate::FileEntry,
+    ) -> crate::Result<()> {
+        let path = current_path.join(String::from_utf8(file.path().clone())?);
+        // All the entries of a hard links set are keyed by the index of the first one.
+        let set_idx = file.hard_link().unwrap_or(file.idx()).into_u64();
+        if file.nlink() > 1 {
+            let target = self.hard_links.borrow().get(&set_idx).cloned();
+            if let Some(target) = target {
+                let member = TarMember {
+                    path: path.to_string(),
+                    entry_type: EntryType::Link,
+                    size: 0,
+                    link_name: Some(target.as_str().as_bytes()),
+                    device: None,
+                };
+                return self.append(member, file, std::io::empty());
+            }
+            self.hard_links.borrow_mut().insert(set_idx, path.clone());
+        }
+        let bytes = super::file_content(self.arx, file)?;
+        let member = TarMember {
+            path: path.to_string(),
+            entry_type: EntryType::Regular,
+            size: file.size().into_u64(),
+            link_name: None,
+            device: None,
+        };
+        self.append(member, file, bytes.stream())
+    }
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &crate::Link) -> crate::Result<()> {
+        let path = current_path.join(String::from_utf8(link.path().clone())?);
+        let member = TarMember {
+            path: path.to_string(),
+            entry_type: EntryType::Symlink,
+            size: 0,
+            link_name: Some(link.target().as_slice()),
+            device: None,
+        };
+        self.append(member, link, std::io::empty())
+    }
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &crate::Special,
+    ) -> crate::Result<()> {
+        let path = current_path.join(String::from_utf8(special.path().clone())?);
+        let entry_type = match special.kind() {
+            SpecialKind::Fifo => EntryType::Fifo,
+            SpecialKind::CharDevice => EntryType::Char,
+            SpecialKind::BlockDevice => EntryType::Block,
+            SpecialKind::Socket => {
+                log::warn!("{path} is a socket, it cannot be stored in a tar.");
+                return Ok(());
+            }
+        };
+        let member = TarMember {
+            path: path.to_string(),
+            entry_type,
+            size: 0,
+            link_name: None,
+            device: Some((special.major(), special.minor())),
+        };
+        self.append(member, special, std::io::empty())
+    }
+}
+
+/// Write the entries of `arx` (or of the directory `root` in `arx`) in `writer` as a tar.
+///
+/// Paths in the tar are relative to `root`.
+pub fn export_tar<W: Write>(arx: &Arx, root: Option<&crate::Path>, writer: W) -> crate::Result<W> {
+    let exporter = TarExporter::new(arx, writer);
+    super::walk(arx, root, &exporter)?;
+    exporter.finish()
+}
@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+use std::io::{Seek, Write};
+
+use crate::{Arx, CommonEntry, FullBuilder};
+use ::zip::write::{FullFileOptions, ZipWriter};
+use ::zip::CompressionMethod;
+
+/// Header id of the extended timestamp extra field.
+const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+
+/// Header id of the Info-ZIP unix extra field (uid/gid).
+const INFOZIP_UNIX_ID: u16 = 0x7875;
+
+fn zip_error(e: ::zip::result::ZipError) -> crate::Error {
+    crate::Error::Io(e.into())
+}
+
+/// Write the entries of an archive as a zip.
+///
+/// Unix mode is stored in the external attributes, mtime in a extended timestamp extra field
+/// and owner and group in a Info-ZIP unix extra field.
+/// Hard links are stored as regular files, special files are skipped.
+///
+/// Non empty contents are deflated: the jubako reader doesn't tell how a content is compressed
+/// in the archive, so we cannot store the contents which were not compressed in it.
+pub struct ZipExporter<'a, W: Write + Seek> {
+    arx: &'a Arx,
+    writer: RefCell<ZipWriter<W>>,
+}
+
+impl<'a, W: Write + Seek> ZipExporter<'a, W> {
+    pub fn new(arx: &'a Arx, writer: W) -> Self {
+        Self {
+            arx,
+            writer: RefCell::new(ZipWriter::new(writer)),
+        }
+    }
+
+    /// Write the central directory and return the writer.
+    pub fn finish(self) -> crate::Result<W> {
+        self.writer.into_inner().finish().map_err(zip_error)
+    }
+
+    fn options(
+        entry: &impl CommonEntry,
+        method: CompressionMethod,
+    ) -> crate::Result<FullFileOptions<'static>> {
+        let mtime = entry.mtime();
+        let mut options = FullFileOptions::default()
+            .compression_method(method)
+            .unix_permissions(entry.rights() as u32);
+        // Dos date (1980-2107) is less precise than the extended timestamp but is mandatory.
+        let date = time::OffsetDateTime::from_unix_timestamp(mtime as i64)
+            .ok()
+            .and_then(|date| ::zip::DateTime::try_from(date).ok());
+        if let Some(date) = date {
+            options = options.last_modified_time(date);
+        }
+
+        // The extended timestamp is a signed 32 bits value, times before 1970 are negative.
+        let mut timestamp = vec![1u8];
+        let signed_mtime = (mtime as i64).clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+        timestamp.extend_from_slice(&signed_mtime.to_le_bytes());
+        options
+            .add_extra_data(EXTENDED_TIMESTAMP_ID, timestamp.into_boxed_slice(), false)
+            .map_err(zip_error)?;
+
+        let mut owner = vec![1u8, 4];
+        owner.extend_from_slice(&entry.owner().to_le_bytes());
+        owner.push(4);
+        owner.extend_from_slice(&entry.group().to_le_bytes());
+        options
+            .add_extra_data(INFOZIP_UNIX_ID, owner.into_boxed_slice(), false)
+            .map_err(zip_error)?;
+        Ok(options)
+    }
+}
+
+impl<W: Write + Seek> crate::walk::Operator<crate::PathBuf, FullBuilder> for ZipExporter<'_, W> {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        current_path.push(String::from_utf8(dir.path().clone())?);
+        let options = Self::options(dir, CompressionMethod::Stored)?;
+        self.writer
+            .borrow_mut()
+            .add_directory(current_path.as_str(), options)
+            .map_err(zip_error)?;
+        Ok(true)
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(
+        &self,
+        current_path: &mut crate::PathBuf,
+        file: &crate::FileEntry,
+    ) -> crate::Result<()> {
+        let path = current_path.join(String::from_utf8(file.path().clone())?);
+        let bytes = super::file_content(self.arx, file)?;
+        let size = file.size().into_u64();
+        let method = if size == 0 {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        let options = Self::options(file, method)?.large_file(size >= u32::MAX as u64);
+        let mut writer = self.writer.borrow_mut();
+        writer
+            .start_file(path.as_str(), options)
+            .map_err(zip_error)?;
+        std::io::copy(&mut bytes.stream(), &mut *writer)?;
+        Ok(())
+    }
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &crate::Link) -> crate::Result<()> {
+        let path = current_path.join(String::from_utf8(link.path().clone())?);
+        let options = Self::options(link, CompressionMethod::Stored)?;
+        self.writer
+            .borrow_mut()
+            .add_symlink(
+                path.as_str(),
+                String::from_utf8_lossy(link.target()),
+                options,
+            )
+            .map_err(zip_error)?;
+        Ok(())
+    }
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &crate::Special,
+    ) -> crate::Result<()> {
+        let path = current_path.join(String::from_utf8(special.path().clone())?);
+        log::warn!("{path} is a special file, it cannot be stored in a zip.");
+        Ok(())
+    }
+}
+
+/// Write the entries of `arx` (or of the directory `root` in `arx`) in `writer` as a zip.
+///
+/// Paths in the zip are relative to `root`.
+pub fn export_zip<W: Write + Seek>(
+    arx: &Arx,
+    root: Option<&crate::Path>,
+    writer: W,
+) -> crate::Result<W> {
+    let exporter = ZipExporter::new(arx, writer);
+    super::walk(arx, root, &exporter)?;
+    exporter.finish()
+}
@@ -0,0 +1,107 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Select entries using include and exclude glob patterns.
+///
+/// Patterns are matched against the whole path of the entries in the archive
+/// (`**/*.so`, `docs/**`, ...). `*` and `?` never match a `/`, use `**` to match
+/// across directories. A entry is selected if it matches one of the include patterns
+/// (or if there is no include pattern) and no exclude pattern.
+/// A pattern `dir/**` also matches `dir` itself. Excluding a directory excludes all its content.
+#[derive(Clone, Debug)]
+pub struct PathFilter {
+    include: GlobSet,
+    // Literal leading components of the include patterns.
+    // None if a include pattern may match anywhere.
+    include_prefixes: Option<Vec<crate::PathBuf>>,
+    exclude: GlobSet,
+}
+
+impl Default for PathFilter {
+    fn default() -> Self {
+        Self {
+            include: GlobSet::empty(),
+            include_prefixes: None,
+            exclude: GlobSet::empty(),
+        }
+    }
+}
+
+fn is_literal(component: &str) -> bool {
+    !component.contains(['*', '?', '[', ']', '{', '}', '\\'])
+}
+
+fn build_set<S: AsRef<str>>(patterns: &[S]) -> crate::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        let mut add = |p: &str| -> crate::Result<()> {
+            let glob = GlobBuilder::new(p)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| crate::Error::InvalidPath(format!("Invalid pattern {p}: {e}")))?;
+            builder.add(glob);
+            Ok(())
+        };
+        add(pattern)?;
+        if let Some(dir) = pattern.strip_suffix("/**") {
+            add(dir)?;
+        }
+    }
+    builder
+        .build()
+        .map_err(|e| crate::Error::InvalidPath(e.to_string()))
+}
+
+impl PathFilter {
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> crate::Result<Self> {
+        let include_prefixes: Option<Vec<crate::PathBuf>> = include
+            .iter()
+            .map(|pattern| {
+                let prefix: Vec<_> = pattern
+                    .as_ref()
+                    .split('/')
+                    .take_while(|c| is_literal(c))
+                    .collect();
+                if prefix.is_empty() {
+                    None
+                } else {
+                    Some(crate::PathBuf::from(prefix.join("/")))
+                }
+            })
+            .collect();
+        Ok(Self {
+            include: build_set(include)?,
+            include_prefixes,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    /// Return true if the filter selects all entries.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Return true if the entry at `path` is selected.
+    pub fn matches(&self, path: &crate::Path) -> bool {
+        (self.include.is_empty() || self.include.is_match(path.as_str()))
+            && !self.exclude.is_match(path.as_str())
+    }
+
+    /// Return true if the directory at `path` may contain selected entries.
+    ///
+    /// Walkers can skip the directory if this returns false.
+    pub fn may_match_under(&self, path: &crate::Path) -> bool {
+        if self.exclude.is_match(path.as_str()) {
+            return false;
+        }
+        if self.include.is_empty() {
+            return true;
+        }
+        match &self.include_prefixes {
+            None => true,
+            Some(prefixes) => prefixes
+                .iter()
+                .any(|prefix| prefix.starts_with(path) || path.starts_with(prefix)),
+        }
+    }
+}
@@ -7,8 +7,17 @@ mod arx_fs;
 pub mod cmd_utils;
 mod common;
 pub mod create;
+pub mod diff;
 mod entry;
+mod error;
+pub mod export;
+mod filter;
+#[cfg(unix)]
+mod safe_dir;
+pub mod stats;
 mod tools;
+mod transform;
+pub mod verify;
 pub mod walk;
 
 pub use arx::Arx;
@@ -16,8 +25,11 @@ pub use arx::Arx;
 pub use arx_fs::{ArxFs, Stats};
 pub use common::{
     AllProperties, Builder, Entry, FromPathError, FromPathErrorKind, FullBuilderTrait, Path,
-    PathBuf, VENDOR_ID,
+    PathBuf, SpecialKind, Xattrs, VENDOR_ID,
 };
 pub use entry::*;
-pub use tools::{extract, extract_arx, extract_arx_range};
+pub use error::{Error, Result};
+pub use filter::PathFilter;
+pub use tools::{extract, extract_arx, extract_arx_range, ExtractOptions, OverwritePolicy};
+pub use transform::PathTransform;
 pub use walk::*;
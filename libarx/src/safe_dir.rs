@@ -0,0 +1,317 @@
+use std::ffi::{CString, OsStr};
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path};
+
+fn c_string(name: &OsStr) -> io::Result<CString> {
+    CString::new(name.as_bytes()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} contains a nul byte", name.to_string_lossy()),
+        )
+    })
+}
+
+fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// What we need to know about an existing entry.
+pub(crate) struct Stat {
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    /// Modification time (seconds, nanoseconds).
+    pub mtime: (i64, u32),
+}
+
+/// A directory opened without following symlinks.
+///
+/// All operations are done relative to the directory (`openat`, `mkdirat`, ...) and never
+/// follow a symlink, so a symlink created after the directory is opened cannot redirect them
+/// elsewhere.
+pub(crate) struct SafeDir(OwnedFd);
+
+impl SafeDir {
+    /// Open `dir`. `dir` itself may be a symlink (it is given by the user).
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        let c_dir = c_string(dir.as_os_str())?;
+        let fd = check(unsafe {
+            libc::open(
+                c_dir.as_ptr(),
+                libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC,
+            )
+        })?;
+        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) }))
+    }
+
+    /// Open the directory `name` in this directory, failing if it is a symlink.
+    ///
+    /// If `create` is true, the directory is created if it doesn't exist.
+    pub fn open_dir(&self, name: &OsStr, create: bool) -> io::Result<Self> {
+        let c_name = c_string(name)?;
+        let open = || {
+            check(unsafe {
+                libc::openat(
+                    self.0.as_raw_fd(),
+                    c_name.as_ptr(),
+                    libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                )
+            })
+        };
+        let fd = match open() {
+            Err(e) if create && e.kind() == io::ErrorKind::NotFound => {
+                match self.create_dir(name) {
+                    // Another thread may have created it meanwhile.
+                    Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                    _ => open()?,
+                }
+            }
+            fd => fd?,
+        };
+        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) }))
+    }
+
+    /// Open the parent directory of `path` (which must be in `base_dir`), one component
+    /// at a time from `base_dir`. Missing directories are created.
+    ///
+    /// Return the parent directory and the file name of `path`.
+    pub fn open_parent<'p>(base_dir: &Path, path: &'p Path) -> crate::Result<(Self, &'p OsStr)> {
+        let not_normalized = || {
+            crate::Error::InvalidPath(format!(
+                "{} is not a normalized path in {}",
+                path.display(),
+                base_dir.display()
+            ))
+        };
+        let relative = path.strip_prefix(base_dir).map_err(|_| not_normalized())?;
+        let mut components = relative.components();
+        let name = match components.next_back() {
+            Some(Component::Normal(name)) => name,
+            _ => return Err(not_normalized()),
+        };
+        let mut dir = Self::open(base_dir)?;
+        for component in components {
+            let component = match component {
+                Component::Normal(c) => c,
+                _ => return Err(not_normalized()),
+            };
+            // Opening a symlink fails with ELOOP or ENOTDIR, depending on the system.
+            dir = match dir.open_dir(component, true) {
+                Ok(sub_dir) => sub_dir,
+                Err(_) if dir.stat(component)?.is_some_and(|s| s.is_symlink) => {
+                    return Err(crate::Error::InvalidPath(format!(
+                        "{} would be written through the symlink {}",
+                        path.display(),
+                        component.to_string_lossy()
+                    )))
+                }
+                Err(e) => return Err(e.into()),
+            };
+        }
+        Ok((dir, name))
+    }
+
+    /// The metadata of `name` (not following symlinks), or None if it doesn't exist.
+    pub fn stat(&self, name: &OsStr) -> io::Result<Option<Stat>> {
+        let c_name = c_string(name)?;
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        let ret = unsafe {
+            libc::fstatat(
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                stat.as_mut_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        match check(ret) {
+            Ok(_) => {
+                let stat = unsafe { stat.assume_init() };
+                Ok(Some(Stat {
+                    is_dir: stat.st_mode & libc::S_IFMT == libc::S_IFDIR,
+                    is_symlink: stat.st_mode & libc::S_IFMT == libc::S_IFLNK,
+                    mtime: (stat.st_mtime as i64, stat.st_mtime_nsec as u32),
+                }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Remove the file (or the empty directory if `is_dir`) `name`.
+    pub fn remove(&self, name: &OsStr, is_dir: bool) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        let flags = if is_dir { libc::AT_REMOVEDIR } else { 0 };
+        check(unsafe { libc::unlinkat(self.0.as_raw_fd(), c_name.as_ptr(), flags) })?;
+        Ok(())
+    }
+
+    /// Rename `name` to `new_name` in this directory.
+    pub fn rename(&self, name: &OsStr, new_name: &OsStr) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        let c_new_name = c_string(new_name)?;
+        check(unsafe {
+            libc::renameat(
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                self.0.as_raw_fd(),
+                c_new_name.as_ptr(),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Create the new file `name`.
+    ///
+    /// Fail if `name` exists, even as a symlink (which is never followed).
+    pub fn create_file(&self, name: &OsStr) -> io::Result<File> {
+        let c_name = c_string(name)?;
+        let fd = check(unsafe {
+            libc::openat(
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                0o666 as libc::c_uint,
+            )
+        })?;
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+
+    pub fn create_dir(&self, name: &OsStr) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        check(unsafe { libc::mkdirat(self.0.as_raw_fd(), c_name.as_ptr(), 0o777) })?;
+        Ok(())
+    }
+
+    pub fn symlink(&self, target: &OsStr, name: &OsStr) -> io::Result<()> {
+        let c_target = c_string(target)?;
+        let c_name = c_string(name)?;
+        check(unsafe { libc::symlinkat(c_target.as_ptr(), self.0.as_raw_fd(), c_name.as_ptr()) })?;
+        Ok(())
+    }
+
+    /// Create `name` as a hard link to `target_name` in `target_dir`.
+    ///
+    /// `target_name` is not followed if it is a symlink.
+    pub fn hard_link(
+        &self,
+        target_dir: &SafeDir,
+        target_name: &OsStr,
+        name: &OsStr,
+    ) -> io::Result<()> {
+        let c_target = c_string(target_name)?;
+        let c_name = c_string(name)?;
+        check(unsafe {
+            libc::linkat(
+                target_dir.0.as_raw_fd(),
+                c_target.as_ptr(),
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                0,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Create the special file `name` (`mode` contains the file type).
+    pub fn mknod(&self, name: &OsStr, mode: libc::mode_t, dev: libc::dev_t) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        check(unsafe { libc::mknodat(self.0.as_raw_fd(), c_name.as_ptr(), mode, dev) })?;
+        Ok(())
+    }
+
+    /// Change the owner and group of `name` (not following symlinks).
+    pub fn chown(&self, name: &OsStr, owner: u32, group: u32) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        check(unsafe {
+            libc::fchownat(
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                owner,
+                group,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Change the rights of `name`. Fail if `name` is a symlink.
+    pub fn chmod(&self, name: &OsStr, mode: libc::mode_t) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        check(unsafe {
+            libc::fchmodat(
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                mode,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Set the access and modification times (seconds, nanoseconds) of `name`
+    /// (not following symlinks). The access time is kept if `atime` is None.
+    pub fn set_times(
+        &self,
+        name: &OsStr,
+        atime: Option<(i64, u32)>,
+        mtime: (i64, u32),
+    ) -> io::Result<()> {
+        let c_name = c_string(name)?;
+        let timespec = |(sec, nsec): (i64, u32)| {
+            let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+            ts.tv_sec = sec as libc::time_t;
+            ts.tv_nsec = nsec as _;
+            ts
+        };
+        let atime = match atime {
+            Some(atime) => timespec(atime),
+            None => {
+                let mut ts = timespec((0, 0));
+                ts.tv_nsec = libc::UTIME_OMIT;
+                ts
+            }
+        };
+        let times = [atime, timespec(mtime)];
+        check(unsafe {
+            libc::utimensat(
+                self.0.as_raw_fd(),
+                c_name.as_ptr(),
+                times.as_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Set the extended attribute `attr` of `name` (not following symlinks).
+    ///
+    /// There is no `*xattrat` function, so the entry is reached through the
+    /// descriptor of the directory in `/proc`.
+    #[cfg(target_os = "linux")]
+    pub fn set_xattr(&self, name: &OsStr, attr: &OsStr, value: &[u8]) -> io::Result<()> {
+        let path = Path::new("/proc/self/fd")
+            .join(self.0.as_raw_fd().to_string())
+            .join(name);
+        // `xattr::set` doesn't follow symlinks.
+        xattr::set(path, attr, value)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn set_xattr(&self, _name: &OsStr, _attr: &OsStr, _value: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "cannot set xattrs relative to a directory on this system",
+        ))
+    }
+
+    /// Use the directory as a file, to change its metadata through its descriptor.
+    pub fn into_file(self) -> File {
+        File::from(self.0)
+    }
+}
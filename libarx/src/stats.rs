@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::{Arx, CommonEntry, FullBuilder, Walker};
+use jbk::reader::MayMissPack;
+
+/// Number of entries of each type.
+#[derive(Debug, Default, Clone)]
+pub struct EntryCounts {
+    pub files: u64,
+    pub dirs: u64,
+    pub links: u64,
+    pub specials: u64,
+    /// Files which are hard links to a previous file (counted also in `files`).
+    pub hard_links: u64,
+}
+
+impl EntryCounts {
+    pub fn total(&self) -> u64 {
+        self.files + self.dirs + self.links + self.specials
+    }
+}
+
+/// A content pack used by the archive.
+///
+/// The jubako reader gives the pack information (uuid, location) only for missing packs and
+/// tells neither the stored size of a pack nor how its clusters are compressed. So only the
+/// uncompressed size of the contents is reported, and uuid and location for missing packs.
+#[derive(Debug, Clone)]
+pub struct PackStats {
+    pub pack_id: u64,
+    /// Number of contents stored in this pack and used by the archive.
+    pub contents: u64,
+    /// Total size (uncompressed) of these contents.
+    pub content_size: u64,
+    /// If the pack cannot be found, its uuid and declared location.
+    pub missing: Option<MissingPack>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MissingPack {
+    pub uuid: String,
+    pub location: String,
+}
+
+/// Statistics about the content of an archive.
+#[derive(Debug, Default, Clone)]
+pub struct ArchiveStats {
+    pub entries: EntryCounts,
+    /// Sum of the size of all files (hard links excluded).
+    pub total_size: u64,
+    /// Sum of the size of the distinct contents actually stored.
+    pub content_size: u64,
+    /// Bytes saved because several files share the same content.
+    pub deduplicated_size: u64,
+    pub packs: Vec<PackStats>,
+    /// Largest files, sorted by decreasing size.
+    pub largest_files: Vec<(crate::PathBuf, u64)>,
+    /// Largest directories (size of all files in it, recursively), sorted by decreasing size.
+    pub largest_dirs: Vec<(crate::PathBuf, u64)>,
+}
+
+impl ArchiveStats {
+    pub fn missing_packs(&self) -> impl Iterator<Item = &PackStats> {
+        self.packs.iter().filter(|p| p.missing.is_some())
+    }
+}
+
+/// Keep the `count` biggest items of `items`.
+fn keep_largest(items: &mut Vec<(crate::PathBuf, u64)>, count: usize) {
+    items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    items.truncate(count);
+}
+
+struct StatsCollector<'a> {
+    arx: &'a Arx,
+    stats: RefCell<ArchiveStats>,
+    // Index of the packs in `stats.packs`.
+    pack_indexes: RefCell<HashMap<u64, usize>>,
+    contents: RefCell<HashSet<(u64, u64)>>,
+    // Size of the directories being walked (the root first).
+    dir_sizes: RefCell<Vec<u64>>,
+    files: RefCell<Vec<(crate::PathBuf, u64)>>,
+    dirs: RefCell<Vec<(crate::PathBuf, u64)>>,
+}
+
+impl StatsCollector<'_> {
+    fn pack_index(&self, content: jbk::ContentAddress) -> crate::Result<usize> {
+        let pack_id = content.pack_id.into_u64();
+        if let Some(idx) = self.pack_indexes.borrow().get(&pack_id) {
+            return Ok(*idx);
+        }
+        // Packs are either fully present or missing, so checking one content is enough.
+        let missing = match self.arx.get_bytes(content)? {
+            MayMissPack::FOUND(_) => None,
+            MayMissPack::MISSING(pack_info) => Some(MissingPack {
+                uuid: pack_info.uuid.to_string(),
+                location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+            }),
+        };
+        let mut stats = self.stats.borrow_mut();
+        stats.packs.push(PackStats {
+            pack_id,
+            contents: 0,
+            content_size: 0,
+            missing,
+        });
+        let idx = stats.packs.len() - 1;
+        self.pack_indexes.borrow_mut().insert(pack_id, idx);
+        Ok(idx)
+    }
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for StatsCollector<'_> {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        current_path.push(String::from_utf8_lossy(dir.path()).as_ref());
+        self.stats.borrow_mut().entries.dirs += 1;
+        self.dir_sizes.borrow_mut().push(0);
+        Ok(true)
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
+        let mut dir_sizes = self.dir_sizes.borrow_mut();
+        let size = dir_sizes.pop().unwrap();
+        *dir_sizes.last_mut().unwrap() += size;
+        self.dirs.borrow_mut().push((current_path.clone(), size));
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(
+        &self,
+        current_path: &mut crate::PathBuf,
+        entry: &crate::FileEntry,
+    ) -> crate::Result<()> {
+        if entry.hard_link().is_some() {
+            let mut stats = self.stats.borrow_mut();
+            stats.entries.files += 1;
+            stats.entries.hard_links += 1;
+            return Ok(());
+        }
+        let size = entry.size().into_u64();
+        let content = entry.content();
+        let pack_index = self.pack_index(content)?;
+        let is_new_content = self
+            .contents
+            .borrow_mut()
+            .insert((content.pack_id.into_u64(), content.content_id.into_u64()));
+
+        let mut stats = self.stats.borrow_mut();
+        stats.entries.files += 1;
+        stats.total_size += size;
+        if is_new_content {
+            stats.content_size += size;
+            let pack = &mut stats.packs[pack_index];
+            pack.contents += 1;
+            pack.content_size += size;
+        } else {
+            stats.deduplicated_size += size;
+        }
+        *self.dir_sizes.borrow_mut().last_mut().unwrap() += size;
+        let path = current_path.join(String::from_utf8_lossy(entry.path()).as_ref());
+        self.files.borrow_mut().push((path, size));
+        Ok(())
+    }
+    fn on_link(
+        &self,
+        _current_path: &mut crate::PathBuf,
+        _link: &crate::Link,
+    ) -> crate::Result<()> {
+        self.stats.borrow_mut().entries.links += 1;
+        Ok(())
+    }
+    fn on_special(
+        &self,
+        _current_path: &mut crate::PathBuf,
+        _special: &crate::Special,
+    ) -> crate::Result<()> {
+        self.stats.borrow_mut().entries.specials += 1;
+        Ok(())
+    }
+}
+
+/// Collect statistics about `arx`.
+///
+/// `largest_count` is the number of largest files and directories to report.
+/// The contents are not read, only the first content of each pack to check
+/// if the pack is missing.
+pub fn stats(arx: &Arx, largest_count: usize) -> crate::Result<ArchiveStats> {
+    let collector = StatsCollector {
+        arx,
+        stats: Default::default(),
+        pack_indexes: Default::default(),
+        contents: Default::default(),
+        dir_sizes: RefCell::new(vec![0]),
+        files: Default::default(),
+        dirs: Default::default(),
+    };
+    let mut walker = Walker::new(arx, Default::default());
+    walker.run(&collector)?;
+    let mut stats = collector.stats.into_inner();
+    stats.largest_files = collector.files.into_inner();
+    keep_largest(&mut stats.largest_files, largest_count);
+    stats.largest_dirs = collector.dirs.into_inner();
+    keep_largest(&mut stats.largest_dirs, largest_count);
+    Ok(stats)
+}
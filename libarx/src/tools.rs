@@ -1,106 +1,697 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::fs::{create_dir, create_dir_all, OpenOptions};
 use std::io::Write;
 #[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
 use std::os::unix::fs::symlink;
 #[cfg(windows)]
 use std::os::windows::fs::symlink_file as symlink;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use crate::{AllProperties, Arx, Builder, Walker};
-use jbk::reader::builder::PropertyBuilderTrait;
-use jbk::reader::ByteSlice;
+#[cfg(unix)]
+use crate::safe_dir::SafeDir;
+use crate::{Arx, CommonEntry, FullBuilder, Walker};
 use jbk::reader::MayMissPack;
 
-struct FileEntry {
-    path: String,
-    content: jbk::ContentAddress,
+/// What to do when a entry to extract already exists on the filesystem.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cmd_utils", derive(clap::ValueEnum))]
+pub enum OverwritePolicy {
+    /// Fail to extract the entry.
+    #[default]
+    Error,
+
+    /// Keep the existing file and don't extract the entry.
+    Skip,
+
+    /// Remove the existing file and extract the entry.
+    Overwrite,
+
+    /// Overwrite the existing file only if it is older than the entry.
+    KeepNewer,
+
+    /// Rename the existing file (as `name.~N~`) and extract the entry.
+    Rename,
 }
 
-struct Link {
-    path: String,
-    target: String,
+/// Options of the extraction.
+///
+/// By default, only the content of the entries is extracted: files and directories are
+/// created with default permissions, current time and belong to the current user.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    /// Restore the rights (including setuid, setgid and sticky bits) of files and directories.
+    pub preserve_mode: bool,
+
+    /// Restore the modification time of files, directories and symlinks.
+    ///
+    /// Access time is also restored if it is stored in the archive.
+    /// Change and creation times cannot be set and are never restored.
+    pub preserve_mtime: bool,
+
+    /// Restore the owner and group of the entries.
+    ///
+    /// Owner and group are numeric ids and they are restored as is.
+    /// This is done only if we are running as root.
+    pub preserve_owner: bool,
+
+    /// Restore the extended attributes of the entries.
+    ///
+    /// Attributes the filesystem refuses (unsupported or not permitted) are reported and skipped.
+    pub preserve_xattrs: bool,
+
+    /// Continue the extraction when a entry fails to be extracted.
+    ///
+    /// By default, extraction stops at the first error.
+    /// In both cases, all errors are returned at the end of the extraction.
+    pub continue_on_error: bool,
+
+    /// What to do if a file to extract already exists.
+    ///
+    /// Existing directories are always reused, whatever the policy.
+    pub overwrite: OverwritePolicy,
+
+    /// Disable the safe extraction mode.
+    ///
+    /// In safe mode (the default), entries with a name which could make them be written
+    /// outside of the output directory (`..`, absolute path, ...) are rejected and nothing
+    /// is written through a symlink (existing or created by the extraction).
+    pub allow_unsafe_paths: bool,
+
+    /// Number of leading components to remove from the path of the entries.
+    ///
+    /// Entries with fewer components are not extracted.
+    pub strip_components: usize,
+
+    /// Transformations to apply (in order) on the path of the entries, after
+    /// `strip_components`.
+    ///
+    /// Entries are still selected (`files_to_extract`) using their path in the archive.
+    pub transforms: Vec<crate::PathTransform>,
+
+    /// Extract only the entries selected by this filter (in addition to `files_to_extract`).
+    ///
+    /// Directories are created only if they may contain selected entries.
+    pub filter: crate::PathFilter,
 }
 
-struct FileBuilder {
-    path_property: jbk::reader::builder::ArrayProperty,
-    content_address_property: jbk::reader::builder::ContentProperty,
+impl ExtractOptions {
+    fn restore_metadata(&self) -> bool {
+        self.preserve_mode || self.preserve_mtime || self.preserve_owner || self.preserve_xattrs
+    }
+
+    /// Rewrite the path of a entry using `strip_components` and `transforms`.
+    ///
+    /// Return None if nothing is left of the path.
+    pub fn rewrite_path(&self, path: &crate::Path) -> Option<crate::PathBuf> {
+        let mut new_path = crate::PathBuf::new();
+        for component in path.iter().skip(self.strip_components) {
+            new_path.push(component);
+        }
+        for transform in &self.transforms {
+            new_path = transform.apply(&new_path);
+        }
+        if new_path.as_str().is_empty() {
+            None
+        } else {
+            Some(new_path)
+        }
+    }
 }
 
-impl Builder for FileBuilder {
-    type Entry = FileEntry;
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
 
-    fn new(properties: &AllProperties) -> Self {
+#[cfg(windows)]
+fn is_root() -> bool {
+    false
+}
+
+/// The metadata of a entry to restore once the entry is extracted.
+#[derive(Clone)]
+struct EntryMetadata {
+    owner: u32,
+    group: u32,
+    rights: u16,
+    mtime: u64,
+    mtime_nsec: u32,
+    atime_ns: Option<u64>,
+    xattrs: crate::Xattrs,
+}
+
+impl EntryMetadata {
+    fn new(entry: &impl CommonEntry) -> Self {
         Self {
-            path_property: properties.path_property.clone(),
-            content_address_property: properties.file_content_address_property,
+            owner: entry.owner(),
+            group: entry.group(),
+            rights: entry.rights(),
+            mtime: entry.mtime(),
+            mtime_nsec: entry.mtime_nsec(),
+            atime_ns: entry.atime_ns(),
+            xattrs: entry.xattrs().clone(),
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
-        let path_prop = self.path_property.create(reader)?;
-        let mut path = vec![];
-        path_prop.resolve_to_vec(&mut path)?;
-        let content = self.content_address_property.create(reader)?;
-        Ok(FileEntry {
-            path: String::from_utf8(path)?,
-            content,
-        })
+    fn mtime(&self) -> (u64, u32) {
+        (self.mtime, self.mtime_nsec)
+    }
+
+    /// Access (if known) and modification times.
+    fn times(&self) -> (Option<filetime::FileTime>, filetime::FileTime) {
+        let mtime = filetime::FileTime::from_unix_time(self.mtime as i64, self.mtime_nsec);
+        let atime = self.atime_ns.map(|t| {
+            filetime::FileTime::from_unix_time(
+                (t / 1_000_000_000) as i64,
+                (t % 1_000_000_000) as u32,
+            )
+        });
+        (atime, mtime)
+    }
+
+    fn restore(&self, path: &Path, options: &ExtractOptions, is_link: bool) -> crate::Result<()> {
+        // Owner must be changed first as chown may clear setuid and setgid bits.
+        #[cfg(unix)]
+        if options.preserve_owner {
+            if is_link {
+                std::os::unix::fs::lchown(path, Some(self.owner), Some(self.group))?;
+            } else {
+                std::os::unix::fs::chown(path, Some(self.owner), Some(self.group))?;
+            }
+        }
+        // Set xattrs after chown as changing owner clears `security.capability`.
+        #[cfg(unix)]
+        if options.preserve_xattrs {
+            for (name, value) in self.xattrs.iter() {
+                let name = std::ffi::OsStr::from_bytes(name);
+                // `xattr::set` doesn't follow symlinks.
+                if let Err(e) = xattr::set(path, name, value) {
+                    log::warn!(
+                        "Cannot set xattr {} on {}: {e}",
+                        name.to_string_lossy(),
+                        path.display()
+                    );
+                }
+            }
+        }
+        // Symlinks have no rights on their own.
+        #[cfg(unix)]
+        if options.preserve_mode && !is_link {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(self.rights as u32))?;
+        }
+        if options.preserve_mtime {
+            let (atime, mtime) = self.times();
+            // Keep the current access time if we don't know it.
+            let atime = match atime {
+                Some(atime) => atime,
+                None => {
+                    filetime::FileTime::from_last_access_time(&std::fs::symlink_metadata(path)?)
+                }
+            };
+            if is_link {
+                filetime::set_symlink_file_times(path, atime, mtime)?;
+            } else {
+                filetime::set_file_times(path, atime, mtime)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as `restore` but relative to the opened parent directory `dir`, so nothing
+    /// can redirect us to another file. `path` is only used in messages.
+    #[cfg(unix)]
+    fn restore_at(
+        &self,
+        dir: &SafeDir,
+        name: &OsStr,
+        path: &Path,
+        options: &ExtractOptions,
+        is_link: bool,
+    ) -> crate::Result<()> {
+        if options.preserve_owner {
+            dir.chown(name, self.owner, self.group)?;
+        }
+        if options.preserve_xattrs {
+            for (attr, value) in self.xattrs.iter() {
+                let attr = OsStr::from_bytes(attr);
+                if let Err(e) = dir.set_xattr(name, attr, value) {
+                    log::warn!(
+                        "Cannot set xattr {} on {}: {e}",
+                        attr.to_string_lossy(),
+                        path.display()
+                    );
+                }
+            }
+        }
+        if options.preserve_mode && !is_link {
+            dir.chmod(name, self.rights as libc::mode_t)?;
+        }
+        if options.preserve_mtime {
+            let atime = self
+                .atime_ns
+                .map(|t| ((t / 1_000_000_000) as i64, (t % 1_000_000_000) as u32));
+            dir.set_times(name, atime, (self.mtime as i64, self.mtime_nsec))?;
+        }
+        Ok(())
+    }
+
+    /// Same as `restore` but through an opened file (or directory), so nothing
+    /// can redirect us to another file. `path` is only used in messages.
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    fn restore_handle(
+        &self,
+        file: &std::fs::File,
+        path: &Path,
+        options: &ExtractOptions,
+    ) -> crate::Result<()> {
+        #[cfg(unix)]
+        if options.preserve_owner {
+            std::os::unix::fs::fchown(file, Some(self.owner), Some(self.group))?;
+        }
+        #[cfg(unix)]
+        if options.preserve_xattrs {
+            use xattr::FileExt;
+            for (name, value) in self.xattrs.iter() {
+                let name = std::ffi::OsStr::from_bytes(name);
+                if let Err(e) = file.set_xattr(name, value) {
+                    log::warn!(
+                        "Cannot set xattr {} on {}: {e}",
+                        name.to_string_lossy(),
+                        path.display()
+                    );
+                }
+            }
+        }
+        #[cfg(unix)]
+        if options.preserve_mode {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(self.rights as u32))?;
+        }
+        if options.preserve_mtime {
+            let (atime, mtime) = self.times();
+            filetime::set_file_handle_times(file, atime, Some(mtime))?;
+        }
+        Ok(())
     }
 }
 
-struct LinkBuilder {
-    path_property: jbk::reader::builder::ArrayProperty,
-    link_property: jbk::reader::builder::ArrayProperty,
+/// Hard links are created once all files are extracted as their target may not
+/// be extracted yet when we walk them.
+#[derive(Default)]
+struct HardLinks {
+    /// Extracted files which are the target of hard links.
+    ///
+    /// Files skipped because of the overwrite policy are not targets: hard links
+    /// must not point to the existing (unrelated) file.
+    targets: HashMap<jbk::EntryIdx, PathBuf>,
+    /// Hard links to create: (target idx, path, content, metadata).
+    links: Vec<(jbk::EntryIdx, PathBuf, jbk::ContentAddress, EntryMetadata)>,
 }
 
-impl Builder for LinkBuilder {
-    type Entry = Link;
+/// Errors of the extraction, with the path of the entry which failed.
+#[derive(Default)]
+struct ExtractErrors(Mutex<Vec<(PathBuf, crate::Error)>>);
 
-    fn new(properties: &AllProperties) -> Self {
-        Self {
-            path_property: properties.path_property.clone(),
-            link_property: properties.link_target_property.clone(),
+impl ExtractErrors {
+    fn push(&self, path: &Path, error: crate::Error) {
+        self.0.lock().unwrap().push((path.to_path_buf(), error));
+    }
+
+    /// Extraction must stop at the first error, unless asked otherwise.
+    fn must_stop(&self, options: &ExtractOptions) -> bool {
+        !options.continue_on_error && !self.0.lock().unwrap().is_empty()
+    }
+
+    /// Stop the extraction on `error` (not related to a specific entry),
+    /// keeping the errors of the entries already processed.
+    fn abort(self, base_dir: &Path, error: crate::Error) -> crate::Error {
+        let mut errors = self.0.into_inner().unwrap();
+        if errors.is_empty() {
+            error
+        } else {
+            errors.push((base_dir.to_path_buf(), error));
+            crate::Error::Extract(errors)
         }
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
-        let path_prop = self.path_property.create(reader)?;
-        let mut path = vec![];
-        path_prop.resolve_to_vec(&mut path)?;
+    fn into_result(self) -> crate::Result<()> {
+        let errors = self.0.into_inner().unwrap();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::Extract(errors))
+        }
+    }
+}
 
-        let target_prop = self.link_property.create(reader)?;
-        let mut target = vec![];
-        target_prop.resolve_to_vec(&mut target)?;
-        Ok(Link {
-            path: String::from_utf8(path)?,
-            target: String::from_utf8(target)?,
-        })
+fn write_file(
+    bytes: jbk::reader::ByteRegion,
+    mut file: std::fs::File,
+) -> crate::Result<std::fs::File> {
+    // Don't use std::io::copy as it use an internal buffer where it read data into before writing in file.
+    // If content is compressed, we already have a buffer. Same thing for uncompress as the cluster is probably mmapped.
+    let size = bytes.size().into_u64();
+    let mut offset = 0;
+    while offset < size {
+        let sub_size = std::cmp::min(size - offset, 4 * 1024) as usize;
+        let written = file.write(&bytes.get_slice(offset.into(), sub_size)?)?;
+        offset += written as u64;
     }
+    Ok(file)
 }
 
-struct DirBuilder {
-    path_property: jbk::reader::builder::ArrayProperty,
+/// The name to rename `name` to when it is backed up (`name.~N~`).
+fn backup_name(name: &OsStr, n: u32) -> OsString {
+    let mut backup = name.to_owned();
+    backup.push(format!(".~{n}~"));
+    backup
 }
 
-impl Builder for DirBuilder {
-    type Entry = String;
+/// Check that `name` is a single normal path component.
+fn check_entry_name(name: &str) -> crate::Result<()> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains('/')
+        && !name.contains('\0');
+    #[cfg(windows)]
+    let valid = valid && !name.contains('\\') && !name.contains(':');
+    if valid {
+        Ok(())
+    } else {
+        Err(crate::Error::InvalidPath(format!(
+            "{name:?} is not a valid entry name"
+        )))
+    }
+}
 
-    fn new(properties: &AllProperties) -> Self {
-        Self {
-            path_property: properties.path_property.clone(),
+/// Check that no parent directory of `path` (relative to `base_dir`) is a symlink.
+///
+/// Parents are checked one by one from `base_dir`, without following symlinks,
+/// so we never write outside of `base_dir` through a symlink.
+/// On unix, [`SafeDir`] is used instead, which doesn't race with the creation of symlinks.
+#[cfg(not(unix))]
+fn check_no_symlink(base_dir: &Path, path: &Path) -> crate::Result<()> {
+    let relative = path.strip_prefix(base_dir).map_err(|_| {
+        crate::Error::InvalidPath(format!(
+            "{} is not in {}",
+            path.display(),
+            base_dir.display()
+        ))
+    })?;
+    let mut current = base_dir.to_path_buf();
+    if let Some(parent) = relative.parent() {
+        for component in parent.components() {
+            match component {
+                std::path::Component::Normal(c) => current.push(c),
+                _ => {
+                    return Err(crate::Error::InvalidPath(format!(
+                        "{} is not a normalized path",
+                        path.display()
+                    )))
+                }
+            }
+            match std::fs::symlink_metadata(&current) {
+                Ok(m) if m.is_symlink() => {
+                    return Err(crate::Error::InvalidPath(format!(
+                        "{} would be written through the symlink {}",
+                        path.display(),
+                        current.display()
+                    )))
+                }
+                Ok(_) => {}
+                // Missing parents will be created as real directories.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A entry already present where a entry is extracted.
+struct Existing {
+    is_dir: bool,
+    /// Modification time (seconds, nanoseconds).
+    mtime: (i64, u32),
+}
+
+/// Where a entry is extracted.
+///
+/// In safe mode, the parent directory is opened one component at a time from the output
+/// directory without following symlinks and the entry is created relative to it.
+/// Files are written by worker threads while the walker creates symlinks, so checking
+/// the path and then using it would let a symlink created in between redirect us
+/// outside of the output directory.
+struct Destination<'p> {
+    path: &'p Path,
+    #[cfg(unix)]
+    safe: Option<(SafeDir, &'p OsStr)>,
+}
+
+impl<'p> Destination<'p> {
+    fn new(base_dir: &Path, path: &'p Path, options: &ExtractOptions) -> crate::Result<Self> {
+        #[cfg(unix)]
+        if !options.allow_unsafe_paths {
+            return Ok(Self {
+                path,
+                safe: Some(SafeDir::open_parent(base_dir, path)?),
+            });
+        }
+        #[cfg(not(unix))]
+        if !options.allow_unsafe_paths {
+            check_no_symlink(base_dir, path)?;
+        }
+        // Parents may be missing if paths are rewritten.
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        Ok(Self {
+            path,
+            #[cfg(unix)]
+            safe: None,
+        })
+    }
+
+    fn existing(&self) -> crate::Result<Option<Existing>> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            return Ok(dir.stat(name)?.map(|stat| Existing {
+                is_dir: stat.is_dir,
+                mtime: stat.mtime,
+            }));
+        }
+        match std::fs::symlink_metadata(self.path) {
+            Ok(m) => {
+                let mtime = filetime::FileTime::from_last_modification_time(&m);
+                Ok(Some(Existing {
+                    is_dir: m.is_dir(),
+                    mtime: (mtime.unix_seconds(), mtime.nanoseconds()),
+                }))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn remove(&self, is_dir: bool) -> crate::Result<()> {
+        // Never remove a whole tree, only empty directories may be replaced.
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            return Ok(dir.remove(name, is_dir)?);
+        }
+        if is_dir {
+            std::fs::remove_dir(self.path)?;
+        } else {
+            std::fs::remove_file(self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Rename the existing entry to a free name (`name.~N~`).
+    fn backup(&self) -> crate::Result<()> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            let mut n = 1;
+            loop {
+                let backup = backup_name(name, n);
+                if dir.stat(&backup)?.is_none() {
+                    return Ok(dir.rename(name, &backup)?);
+                }
+                n += 1;
+            }
+        }
+        let mut n = 1;
+        loop {
+            let backup = PathBuf::from(backup_name(self.path.as_os_str(), n));
+            if std::fs::symlink_metadata(&backup).is_err() {
+                return Ok(std::fs::rename(self.path, backup)?);
+            }
+            n += 1;
+        }
+    }
+
+    /// Make room for a entry (with modification time `mtime` and `mtime_nsec`)
+    /// according to the overwrite policy.
+    ///
+    /// Return false if the entry must not be extracted.
+    fn make_room(
+        &self,
+        (mtime, mtime_nsec): (u64, u32),
+        options: &ExtractOptions,
+    ) -> crate::Result<bool> {
+        let existing = match self.existing()? {
+            Some(existing) => existing,
+            None => return Ok(true),
+        };
+        match options.overwrite {
+            OverwritePolicy::Error => Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists", self.path.display()),
+            )
+            .into()),
+            OverwritePolicy::Skip => Ok(false),
+            OverwritePolicy::Overwrite => {
+                self.remove(existing.is_dir)?;
+                Ok(true)
+            }
+            OverwritePolicy::KeepNewer => {
+                if existing.mtime >= (mtime as i64, mtime_nsec) {
+                    Ok(false)
+                } else {
+                    self.remove(existing.is_dir)?;
+                    Ok(true)
+                }
+            }
+            OverwritePolicy::Rename => {
+                self.backup()?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Create the file. Fail if it already exists (or is a symlink, in safe mode).
+    fn create_file(&self) -> crate::Result<std::fs::File> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            return Ok(dir.create_file(name)?);
+        }
+        Ok(OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.path)?)
+    }
+
+    fn create_dir(&self) -> crate::Result<()> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            return Ok(dir.create_dir(name)?);
+        }
+        Ok(create_dir(self.path)?)
+    }
+
+    fn symlink(&self, target: &str) -> crate::Result<()> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            return Ok(dir.symlink(OsStr::new(target), name)?);
+        }
+        Ok(symlink(PathBuf::from(target), self.path)?)
+    }
+
+    /// Create the entry as a hard link to `target` (in `base_dir`).
+    fn hard_link(&self, base_dir: &Path, target: &Path) -> crate::Result<()> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            // The target is reached the same way as the link, never through a symlink.
+            let (target_dir, target_name) = SafeDir::open_parent(base_dir, target)?;
+            return Ok(dir.hard_link(&target_dir, target_name, name)?);
         }
+        #[cfg(not(unix))]
+        let _ = base_dir;
+        Ok(std::fs::hard_link(target, self.path)?)
     }
 
-    fn create_entry(&self, _idx: jbk::EntryIdx, reader: &ByteSlice) -> jbk::Result<Self::Entry> {
-        let path_prop = self.path_property.create(reader)?;
-        let mut path = vec![];
-        path_prop.resolve_to_vec(&mut path)?;
-        Ok(String::from_utf8(path)?)
+    /// Restore the metadata of the created (not regular file) entry.
+    fn restore_metadata(
+        &self,
+        metadata: &EntryMetadata,
+        options: &ExtractOptions,
+        is_link: bool,
+    ) -> crate::Result<()> {
+        #[cfg(unix)]
+        if let Some((dir, name)) = &self.safe {
+            return metadata.restore_at(dir, name, self.path, options, is_link);
+        }
+        metadata.restore(self.path, options, is_link)
+    }
+
+    /// Create a special file (`mode` contains the file type).
+    #[cfg(unix)]
+    fn mknod(&self, mode: libc::mode_t, dev: libc::dev_t) -> crate::Result<()> {
+        if let Some((dir, name)) = &self.safe {
+            return Ok(dir.mknod(name, mode, dev)?);
+        }
+        let c_path = std::ffi::CString::new(self.path.as_os_str().as_bytes()).map_err(|_| {
+            crate::Error::InvalidPath(format!("{} contains a nul byte", self.path.display()))
+        })?;
+        if unsafe { libc::mknod(c_path.as_ptr(), mode, dev) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
     }
 }
 
-type FullBuilder = (FileBuilder, LinkBuilder, DirBuilder);
+/// Open the destination of a entry (with modification time `mtime`) to extract at `path`
+/// and make room for it.
+///
+/// Return None if the entry must not be extracted.
+fn prepare_path<'p>(
+    base_dir: &Path,
+    path: &'p Path,
+    mtime: (u64, u32),
+    options: &ExtractOptions,
+) -> crate::Result<Option<Destination<'p>>> {
+    let destination = Destination::new(base_dir, path, options)?;
+    if destination.make_room(mtime, options)? {
+        Ok(Some(destination))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Write the content of a file entry at `abs_path` and restore its metadata.
+///
+/// Return false if the file is skipped because of the overwrite policy.
+fn extract_file(
+    bytes: MayMissPack<jbk::reader::ByteRegion>,
+    base_dir: &Path,
+    abs_path: &Path,
+    metadata: &EntryMetadata,
+    options: &ExtractOptions,
+) -> crate::Result<bool> {
+    let destination = match prepare_path(base_dir, abs_path, metadata.mtime(), options)? {
+        Some(destination) => destination,
+        None => return Ok(false),
+    };
+    let file = match bytes {
+        MayMissPack::FOUND(bytes) => write_file(bytes, destination.create_file()?)?,
+        MayMissPack::MISSING(pack_info) => {
+            return Err(crate::Error::MissingPack {
+                pack_uuid: pack_info.uuid.to_string(),
+                pack_location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+            })
+        }
+    };
+    if options.restore_metadata() {
+        metadata.restore_handle(&file, abs_path, options)?;
+    }
+    Ok(true)
+}
 
 struct Extractor<'a, 'scope>
 where
@@ -112,10 +703,24 @@ where
     base_dir: PathBuf,
     print_progress: bool,
     recurse: bool,
+    options: &'a ExtractOptions,
+    // Directories metadata must be restored once all their children are written.
+    dirs: &'a Mutex<Vec<(PathBuf, EntryMetadata)>>,
+    hard_links: &'a Mutex<HardLinks>,
+    errors: &'a ExtractErrors,
 }
 
 impl Extractor<'_, '_> {
     fn should_extract(&self, current_file: &crate::Path, is_dir: bool) -> bool {
+        let filtered = if is_dir {
+            self.options.filter.may_match_under(current_file)
+        } else {
+            self.options.filter.matches(current_file)
+        };
+        filtered && self.is_selected(current_file, is_dir)
+    }
+
+    fn is_selected(&self, current_file: &crate::Path, is_dir: bool) -> bool {
         if self.files.is_empty() {
             return true;
         }
@@ -149,8 +754,44 @@ impl Extractor<'_, '_> {
         }
         false
     }
-    fn abs_path(&self, current_file: &crate::Path) -> PathBuf {
-        current_file.to_path(&self.base_dir)
+    /// The path where `current_file` must be extracted.
+    ///
+    /// Return None if the whole path is stripped (so there is nothing to extract).
+    fn abs_path(&self, current_file: &crate::Path) -> crate::Result<Option<PathBuf>> {
+        let path = match self.options.rewrite_path(current_file) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        if !self.options.allow_unsafe_paths {
+            // Transformations may have introduced unsafe components.
+            path.as_str().split('/').try_for_each(check_entry_name)?;
+        }
+        Ok(Some(path.to_path(&self.base_dir)))
+    }
+
+    /// Same as `abs_path` but errors are recorded and None is returned
+    /// if the entry must be skipped.
+    fn target_path(&self, current_file: &crate::Path) -> Option<PathBuf> {
+        match self.abs_path(current_file) {
+            Ok(p) => p,
+            Err(e) => {
+                self.errors.push(&current_file.to_path(&self.base_dir), e);
+                None
+            }
+        }
+    }
+
+    fn must_stop(&self) -> bool {
+        self.errors.must_stop(self.options)
+    }
+
+    /// Check the name of a entry to extract (in safe mode).
+    fn check_name(&self, name: &str) -> crate::Result<()> {
+        if self.options.allow_unsafe_paths {
+            Ok(())
+        } else {
+            check_entry_name(name)
+        }
     }
 }
 
@@ -158,119 +799,285 @@ impl<'a, 'scope> crate::walk::Operator<crate::PathBuf, FullBuilder> for Extracto
 where
     'a: 'scope,
 {
-    fn on_start(&self, _current_path: &mut crate::PathBuf) -> jbk::Result<()> {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
         create_dir_all(&self.base_dir)?;
         Ok(())
     }
 
-    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> jbk::Result<()> {
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
         Ok(())
     }
 
     fn on_directory_enter(
         &self,
         current_path: &mut crate::PathBuf,
-        path: &String,
-    ) -> jbk::Result<bool> {
-        current_path.push(path);
-        if !self.should_extract(current_path, true) {
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        let name = String::from_utf8(dir.path().clone())?;
+        current_path.push(&name);
+        if self.must_stop() || !self.should_extract(current_path, true) {
             return Ok(false);
         }
-        let abs_path = self.abs_path(current_path);
-        if !abs_path.try_exists()? {
-            create_dir(&abs_path)?;
+        let abs_path = match self.abs_path(current_path) {
+            Ok(Some(p)) => p,
+            // The directory is stripped but its children may not be.
+            Ok(None) => return Ok(true),
+            Err(e) => {
+                self.errors.push(&current_path.to_path(&self.base_dir), e);
+                return Ok(false);
+            }
+        };
+        let created = || -> crate::Result<bool> {
+            self.check_name(&name)?;
+            let destination = Destination::new(&self.base_dir, &abs_path, self.options)?;
+            // Existing directories are reused, other files are handled by the overwrite policy.
+            if destination.existing()?.is_some_and(|e| e.is_dir) {
+                return Ok(true);
+            }
+            if !destination.make_room((dir.mtime(), dir.mtime_nsec()), self.options)? {
+                return Ok(false);
+            }
+            destination.create_dir()?;
             if self.print_progress {
                 println!("{}", abs_path.display());
             }
+            Ok(true)
+        };
+        match created() {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(e) => {
+                // We cannot extract anything in this directory.
+                self.errors.push(&abs_path, e);
+                return Ok(false);
+            }
+        }
+        if self.options.restore_metadata() {
+            self.dirs
+                .lock()
+                .unwrap()
+                .push((abs_path, EntryMetadata::new(dir)));
         }
         Ok(true)
     }
     fn on_directory_exit(
         &self,
         current_path: &mut crate::PathBuf,
-        _path: &String,
-    ) -> jbk::Result<()> {
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
         current_path.pop();
         Ok(())
     }
 
-    fn on_file(&self, current_path: &mut crate::PathBuf, entry: &FileEntry) -> jbk::Result<()> {
+    fn on_file(
+        &self,
+        current_path: &mut crate::PathBuf,
+        entry: &crate::FileEntry,
+    ) -> crate::Result<()> {
         let mut current_path = current_path.clone();
-        current_path.push(&entry.path);
-        let entry_content = entry.content;
-        let abs_path = self.abs_path(&current_path);
+        let name = String::from_utf8(entry.path().clone())?;
+        current_path.push(&name);
+        let entry_content = entry.content();
         let print_progress = self.print_progress;
         let arx = self.arx;
-        if !self.should_extract(&current_path, false) {
+        if self.must_stop() || !self.should_extract(&current_path, false) {
+            return Ok(());
+        }
+        let abs_path = match self.target_path(&current_path) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        if let Err(e) = self.check_name(&name) {
+            self.errors.push(&abs_path, e);
+            return Ok(());
+        }
+        let metadata = EntryMetadata::new(entry);
+        if let Some(target) = entry.hard_link() {
+            self.hard_links
+                .lock()
+                .unwrap()
+                .links
+                .push((target, abs_path, entry_content, metadata));
             return Ok(());
         }
-        let bytes = arx.container.get_bytes(entry_content).unwrap();
+        let is_link_target = entry.nlink() > 1;
+        let idx = entry.idx();
+        let bytes = arx.container.get_bytes(entry_content)?;
+        let options = self.options;
+        let errors = self.errors;
+        let hard_links = self.hard_links;
+        let base_dir = self.base_dir.clone();
 
         self.scope.spawn(move |_scope| {
-            match bytes {
-                MayMissPack::FOUND(bytes) => {
-                    let abs_path = abs_path.clone();
-                    let mut file = OpenOptions::new()
-                        .write(true)
-                        .create_new(true)
-                        .open(abs_path)
-                        .unwrap();
-
-                    // Don't use std::io::copy as it use an internal buffer where it read data into before writing in file.
-                    // If content is compressed, we already have a buffer. Same thing for uncompress as the cluster is probably mmapped.
-                    let size = bytes.size().into_u64();
-                    let mut offset = 0;
-                    loop {
-                        let sub_size = std::cmp::min(size - offset, 4 * 1024) as usize;
-                        let written = file
-                            .write(&bytes.get_slice(offset.into(), sub_size).unwrap())
-                            .unwrap();
-                        offset += written as u64;
-                        if offset == size {
-                            break;
-                        }
+            if errors.must_stop(options) {
+                return;
+            }
+            match extract_file(bytes, &base_dir, &abs_path, &metadata, options) {
+                Ok(extracted) => {
+                    if extracted && is_link_target {
+                        hard_links
+                            .lock()
+                            .unwrap()
+                            .targets
+                            .insert(idx, abs_path.clone());
+                    }
+                    if extracted && print_progress {
+                        println!("{}", abs_path.display());
                     }
                 }
-                MayMissPack::MISSING(pack_info) => {
-                    eprintln!(
-                        "Missing pack {} for {}. Declared location is {}",
-                        pack_info.uuid,
-                        abs_path.display(),
-                        String::from_utf8_lossy(&pack_info.pack_location)
-                    );
-                }
-            }
-            if print_progress {
-                println!("{}", abs_path.display());
+                Err(e) => errors.push(&abs_path, e),
             }
         });
         Ok(())
     }
-    fn on_link(&self, current_path: &mut crate::PathBuf, link: &Link) -> jbk::Result<()> {
-        current_path.push(&link.path);
-        if !self.should_extract(current_path, false) {
+    fn on_link(&self, current_path: &mut crate::PathBuf, link: &crate::Link) -> crate::Result<()> {
+        let name = String::from_utf8(link.path().clone())?;
+        current_path.push(&name);
+        if self.must_stop() || !self.should_extract(current_path, false) {
             current_path.pop();
             return Ok(());
         }
-        let abs_path = self.abs_path(current_path);
-        symlink(PathBuf::from(&link.target), PathBuf::from(&abs_path))?;
-        if self.print_progress {
-            println!("{}", abs_path.display());
+        let abs_path = match self.target_path(current_path) {
+            Some(p) => p,
+            None => {
+                current_path.pop();
+                return Ok(());
+            }
+        };
+        let extracted = || -> crate::Result<bool> {
+            self.check_name(&name)?;
+            let destination = match prepare_path(
+                &self.base_dir,
+                &abs_path,
+                (link.mtime(), link.mtime_nsec()),
+                self.options,
+            )? {
+                Some(destination) => destination,
+                None => return Ok(false),
+            };
+            let target = String::from_utf8(link.target().clone())?;
+            destination.symlink(&target)?;
+            if self.options.restore_metadata() {
+                destination.restore_metadata(&EntryMetadata::new(link), self.options, true)?;
+            }
+            Ok(true)
+        };
+        match extracted() {
+            Ok(extracted) => {
+                if extracted && self.print_progress {
+                    println!("{}", abs_path.display());
+                }
+            }
+            Err(e) => self.errors.push(&abs_path, e),
+        }
+        current_path.pop();
+        Ok(())
+    }
+
+    fn on_special(
+        &self,
+        current_path: &mut crate::PathBuf,
+        special: &crate::Special,
+    ) -> crate::Result<()> {
+        let name = String::from_utf8(special.path().clone())?;
+        current_path.push(&name);
+        if self.must_stop() || !self.should_extract(current_path, false) {
+            current_path.pop();
+            return Ok(());
+        }
+        let abs_path = match self.target_path(current_path) {
+            Some(p) => p,
+            None => {
+                current_path.pop();
+                return Ok(());
+            }
+        };
+        let extracted = || -> crate::Result<bool> {
+            self.check_name(&name)?;
+            let destination = match prepare_path(
+                &self.base_dir,
+                &abs_path,
+                (special.mtime(), special.mtime_nsec()),
+                self.options,
+            )? {
+                Some(destination) => destination,
+                None => return Ok(false),
+            };
+            if !create_special(&destination, special)? {
+                return Ok(false);
+            }
+            if self.options.restore_metadata() {
+                destination.restore_metadata(&EntryMetadata::new(special), self.options, false)?;
+            }
+            Ok(true)
+        };
+        match extracted() {
+            Ok(created) => {
+                if created && self.print_progress {
+                    println!("{}", abs_path.display());
+                }
+            }
+            Err(e) => self.errors.push(&abs_path, e),
         }
         current_path.pop();
         Ok(())
     }
 }
 
+/// Create the special file `special` at `destination`.
+///
+/// Return false if the special file cannot be created (and so is skipped).
+#[cfg(unix)]
+fn create_special(destination: &Destination, special: &crate::Special) -> crate::Result<bool> {
+    use crate::SpecialKind;
+    let path = destination.path;
+    let mode = special.rights() as libc::mode_t;
+    match special.kind() {
+        SpecialKind::Fifo => destination.mknod(libc::S_IFIFO | mode, 0)?,
+        SpecialKind::CharDevice | SpecialKind::BlockDevice => {
+            if !is_root() {
+                log::warn!(
+                    "Not running as root, device {} is not created.",
+                    path.display()
+                );
+                return Ok(false);
+            }
+            let file_type = if special.kind() == SpecialKind::CharDevice {
+                libc::S_IFCHR
+            } else {
+                libc::S_IFBLK
+            };
+            let dev = libc::makedev(special.major() as _, special.minor() as _);
+            destination.mknod(file_type | mode, dev)?
+        }
+        SpecialKind::Socket => {
+            // A socket is meaningless without the process listening on it.
+            log::warn!("{} is a socket, it is not created.", path.display());
+            return Ok(false);
+        }
+    };
+    Ok(true)
+}
+
+#[cfg(windows)]
+fn create_special(destination: &Destination, _special: &crate::Special) -> crate::Result<bool> {
+    log::warn!(
+        "Special files are not supported on Windows, {} is not created.",
+        destination.path.display()
+    );
+    Ok(false)
+}
+
 pub fn extract(
     infile: &Path,
     outdir: &Path,
     files_to_extract: HashSet<crate::PathBuf>,
     recurse: bool,
     progress: bool,
-) -> jbk::Result<()> {
+    options: &ExtractOptions,
+) -> crate::Result<()> {
     let arx = Arx::new(infile)?;
-    extract_arx(&arx, outdir, files_to_extract, recurse, progress)
+    extract_arx(&arx, outdir, files_to_extract, recurse, progress, options)
 }
 
 pub fn extract_arx(
@@ -279,9 +1086,14 @@ pub fn extract_arx(
     files_to_extract: HashSet<crate::PathBuf>,
     recurse: bool,
     progress: bool,
-) -> jbk::Result<()> {
+    options: &ExtractOptions,
+) -> crate::Result<()> {
+    let options = check_options(options);
+    let dirs = Mutex::new(Vec::new());
+    let hard_links = Mutex::new(HardLinks::default());
+    let errors = ExtractErrors::default();
     let mut walker = Walker::new(arx, Default::default());
-    rayon::scope(|scope| {
+    let result = rayon::scope(|scope| {
         let extractor = Extractor {
             arx,
             scope,
@@ -289,9 +1101,19 @@ pub fn extract_arx(
             base_dir: outdir.to_path_buf(),
             print_progress: progress,
             recurse,
+            options: &options,
+            dirs: &dirs,
+            hard_links: &hard_links,
+            errors: &errors,
         };
         walker.run(&extractor)
-    })
+    });
+    if let Err(e) = result {
+        return Err(errors.abort(outdir, e));
+    }
+    create_hard_links(arx, outdir, hard_links, progress, &options, &errors);
+    restore_dirs_metadata(outdir, dirs, &options, &errors);
+    errors.into_result()
 }
 
 pub fn extract_arx_range<R: jbk::reader::Range + Sync>(
@@ -301,9 +1123,14 @@ pub fn extract_arx_range<R: jbk::reader::Range + Sync>(
     files_to_extract: HashSet<crate::PathBuf>,
     recurse: bool,
     progress: bool,
-) -> jbk::Result<()> {
+    options: &ExtractOptions,
+) -> crate::Result<()> {
+    let options = check_options(options);
+    let dirs = Mutex::new(Vec::new());
+    let hard_links = Mutex::new(HardLinks::default());
+    let errors = ExtractErrors::default();
     let mut walker = Walker::new(arx, Default::default());
-    rayon::scope(|scope| {
+    let result = rayon::scope(|scope| {
         let extractor = Extractor {
             arx,
             scope,
@@ -311,7 +1138,109 @@ pub fn extract_arx_range<R: jbk::reader::Range + Sync>(
             base_dir: outdir.to_path_buf(),
             print_progress: progress,
             recurse,
+            options: &options,
+            dirs: &dirs,
+            hard_links: &hard_links,
+            errors: &errors,
         };
         walker.run_from_range(&extractor, range)
-    })
+    });
+    if let Err(e) = result {
+        return Err(errors.abort(outdir, e));
+    }
+    create_hard_links(arx, outdir, hard_links, progress, &options, &errors);
+    restore_dirs_metadata(outdir, dirs, &options, &errors);
+    errors.into_result()
+}
+
+fn check_options(options: &ExtractOptions) -> ExtractOptions {
+    let mut options = options.clone();
+    if options.preserve_owner && !is_root() {
+        log::warn!("Not running as root, owner and group of extracted entries are not restored.");
+        options.preserve_owner = false;
+    }
+    options
+}
+
+fn create_hard_links(
+    arx: &Arx,
+    base_dir: &Path,
+    hard_links: Mutex<HardLinks>,
+    progress: bool,
+    options: &ExtractOptions,
+    errors: &ExtractErrors,
+) {
+    let HardLinks { mut targets, links } = hard_links.into_inner().unwrap();
+    for (target, abs_path, content, metadata) in links {
+        if errors.must_stop(options) {
+            return;
+        }
+        let mut created = || -> crate::Result<bool> {
+            match targets.get(&target) {
+                Some(target_path) => {
+                    match prepare_path(base_dir, &abs_path, metadata.mtime(), options)? {
+                        Some(destination) => destination.hard_link(base_dir, target_path)?,
+                        None => return Ok(false),
+                    }
+                    Ok(true)
+                }
+                None => {
+                    // Target has not been extracted (or has been skipped),
+                    // the first link extracted takes its place.
+                    let bytes = arx.container.get_bytes(content)?;
+                    let extracted = extract_file(bytes, base_dir, &abs_path, &metadata, options)?;
+                    if extracted {
+                        targets.insert(target, abs_path.clone());
+                    }
+                    Ok(extracted)
+                }
+            }
+        };
+        match created() {
+            Ok(extracted) => {
+                if extracted && progress {
+                    println!("{}", abs_path.display());
+                }
+            }
+            Err(e) => errors.push(&abs_path, e),
+        }
+    }
+}
+
+fn restore_dir_metadata(
+    base_dir: &Path,
+    path: &Path,
+    metadata: &EntryMetadata,
+    options: &ExtractOptions,
+) -> crate::Result<()> {
+    // The directory may have been replaced by a symlink since it was created,
+    // so open it without following symlinks.
+    #[cfg(unix)]
+    if !options.allow_unsafe_paths {
+        let (parent, name) = SafeDir::open_parent(base_dir, path)?;
+        let dir = parent.open_dir(name, false)?.into_file();
+        return metadata.restore_handle(&dir, path, options);
+    }
+    #[cfg(not(unix))]
+    let _ = base_dir;
+    metadata.restore(path, options, false)
+}
+
+fn restore_dirs_metadata(
+    base_dir: &Path,
+    dirs: Mutex<Vec<(PathBuf, EntryMetadata)>>,
+    options: &ExtractOptions,
+    errors: &ExtractErrors,
+) {
+    // All files are written now, we can restore directories metadata.
+    // Do it from the deepest directory to the top one, so a read only directory
+    // doesn't prevent us to change its children.
+    for (path, metadata) in dirs.into_inner().unwrap().iter().rev() {
+        if errors.must_stop(options) {
+            return;
+        }
+        if let Err(e) = restore_dir_metadata(base_dir, path, metadata, options) {
+            errors.push(path, e);
+        }
+    }
 }
@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+/// A transformation of the paths of the entries at extraction.
+#[derive(Clone, Debug)]
+pub enum PathTransform {
+    /// Replace the leading components `from` by `to`.
+    Prefix {
+        from: crate::PathBuf,
+        to: crate::PathBuf,
+    },
+
+    /// Replace the first match (or all matches if `global`) of `regex` by `replacement`.
+    ///
+    /// `replacement` may refer to capture groups (`$1`, `${name}`).
+    Regex {
+        regex: regex::Regex,
+        replacement: String,
+        global: bool,
+    },
+}
+
+impl PathTransform {
+    /// Apply the transformation on `path`.
+    ///
+    /// Paths not matching the transformation are returned unchanged.
+    pub fn apply(&self, path: &crate::Path) -> crate::PathBuf {
+        match self {
+            Self::Prefix { from, to } => match path.strip_prefix(from) {
+                Ok(rest) => to.join(rest),
+                Err(_) => path.to_owned(),
+            },
+            Self::Regex {
+                regex,
+                replacement,
+                global,
+            } => {
+                let new_path = if *global {
+                    regex.replace_all(path.as_str(), replacement.as_str())
+                } else {
+                    regex.replace(path.as_str(), replacement.as_str())
+                };
+                new_path.as_ref().into()
+            }
+        }
+    }
+
+    fn parse_prefix(s: &str) -> Result<Self, String> {
+        match s.split_once('=') {
+            Some((from, to)) => Ok(Self::Prefix {
+                from: from.into(),
+                to: to.into(),
+            }),
+            None => Err(format!(
+                "Invalid transform {s}. Must be `s/REGEX/REPLACEMENT/[g]` or `FROM=TO`"
+            )),
+        }
+    }
+}
+
+impl FromStr for PathTransform {
+    type Err = String;
+
+    /// Parse a transformation.
+    ///
+    /// Accepted syntaxes are `s/REGEX/REPLACEMENT/[g]` (any character may be used as separator
+    /// instead of `/`) and `FROM=TO` to replace the leading components `FROM` by `TO`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(expr) = s.strip_prefix('s') {
+            let separator = match expr.chars().next() {
+                Some(c) if !c.is_alphanumeric() => c,
+                _ => return Self::parse_prefix(s),
+            };
+            let parts: Vec<&str> = expr[separator.len_utf8()..].split(separator).collect();
+            let (pattern, replacement, flags) = match parts.as_slice() {
+                [pattern, replacement, flags] => (pattern, replacement, flags),
+                _ => return Err(format!("Invalid transform expression {s}")),
+            };
+            let global = match *flags {
+                "" => false,
+                "g" => true,
+                _ => return Err(format!("Invalid flags {flags} in transform expression {s}")),
+            };
+            let regex = regex::Regex::new(pattern).map_err(|e| e.to_string())?;
+            Ok(Self::Regex {
+                regex,
+                replacement: replacement.to_string(),
+                global,
+            })
+        } else {
+            Self::parse_prefix(s)
+        }
+    }
+}
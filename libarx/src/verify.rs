@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+
+use crate::{Arx, CommonEntry, FullBuilder, Walker};
+use jbk::reader::MayMissPack;
+
+/// A problem found while verifying an archive.
+#[derive(Debug)]
+pub enum VerifyIssue {
+    /// The pack containing the content of the entry is missing.
+    MissingPack {
+        path: crate::PathBuf,
+        pack_uuid: String,
+        pack_location: String,
+    },
+    /// The content of the entry doesn't match the stored checksum.
+    Corrupted { path: crate::PathBuf },
+    /// The content of the entry cannot be read (decompression error, io error, ...).
+    ReadError { path: crate::PathBuf, error: String },
+}
+
+impl std::fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MissingPack {
+                path,
+                pack_uuid,
+                pack_location,
+            } => write!(
+                f,
+                "Missing pack {pack_uuid} for {path}. Declared location is {pack_location}"
+            ),
+            Self::Corrupted { path } => write!(f, "Content of {path} is corrupted"),
+            Self::ReadError { path, error } => write!(f, "Cannot read {path}: {error}"),
+        }
+    }
+}
+
+/// The result of the verification of an archive.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// Number of file entries checked.
+    pub checked_files: u64,
+    /// Number of file entries without stored checksum.
+    /// Their content is read but cannot be compared.
+    pub without_checksum: u64,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+struct Verifier<'a> {
+    arx: &'a Arx,
+    report: RefCell<VerifyReport>,
+}
+
+impl Verifier<'_> {
+    fn check_file(&self, path: &crate::Path, entry: &crate::FileEntry) -> Option<VerifyIssue> {
+        let bytes = match self.arx.get_bytes(entry.content()) {
+            Err(e) => {
+                return Some(VerifyIssue::ReadError {
+                    path: path.to_owned(),
+                    error: e.to_string(),
+                })
+            }
+            Ok(MayMissPack::MISSING(pack_info)) => {
+                return Some(VerifyIssue::MissingPack {
+                    path: path.to_owned(),
+                    pack_uuid: pack_info.uuid.to_string(),
+                    pack_location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+                })
+            }
+            Ok(MayMissPack::FOUND(bytes)) => bytes,
+        };
+        let mut hasher = blake3::Hasher::new();
+        if let Err(e) = std::io::copy(&mut bytes.stream(), &mut hasher) {
+            return Some(VerifyIssue::ReadError {
+                path: path.to_owned(),
+                error: e.to_string(),
+            });
+        }
+        match entry.checksum() {
+            None => {
+                self.report.borrow_mut().without_checksum += 1;
+                None
+            }
+            Some(checksum) if hasher.finalize().as_bytes() != checksum => {
+                Some(VerifyIssue::Corrupted {
+                    path: path.to_owned(),
+                })
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+impl crate::walk::Operator<crate::PathBuf, FullBuilder> for Verifier<'_> {
+    fn on_start(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_stop(&self, _current_path: &mut crate::PathBuf) -> crate::Result<()> {
+        Ok(())
+    }
+    fn on_directory_enter(
+        &self,
+        current_path: &mut crate::PathBuf,
+        dir: &crate::Dir,
+    ) -> crate::Result<bool> {
+        current_path.push(String::from_utf8_lossy(dir.path()).as_ref());
+        Ok(true)
+    }
+    fn on_directory_exit(
+        &self,
+        current_path: &mut crate::PathBuf,
+        _dir: &crate::Dir,
+    ) -> crate::Result<()> {
+        current_path.pop();
+        Ok(())
+    }
+    fn on_file(
+        &self,
+        current_path: &mut crate::PathBuf,
+        entry: &crate::FileEntry,
+    ) -> crate::Result<()> {
+        current_path.push(String::from_utf8_lossy(entry.path()).as_ref());
+        let issue = self.check_file(current_path, entry);
+        let mut report = self.report.borrow_mut();
+        report.checked_files += 1;
+        if let Some(issue) = issue {
+            report.issues.push(issue);
+        }
+        current_path.pop();
+        Ok(())
+    }
+    fn on_link(
+        &self,
+        _current_path: &mut crate::PathBuf,
+        _link: &crate::Link,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// Verify the content of all files in the archive.
+///
+/// Each content is fully read (so decompression errors are detected) and
+/// compared to the stored checksum if there is one.
+pub fn verify(arx: &Arx) -> crate::Result<VerifyReport> {
+    let verifier = Verifier {
+        arx,
+        report: Default::default(),
+    };
+    let mut walker = Walker::new(arx, Default::default());
+    walker.run(&verifier)?;
+    Ok(verifier.report.into_inner())
+}
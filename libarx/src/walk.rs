@@ -3,28 +3,38 @@ use super::Arx;
 use jbk::reader::Range;
 
 pub trait Operator<Context, Builder: FullBuilderTrait> {
-    fn on_start(&self, context: &mut Context) -> jbk::Result<()>;
-    fn on_stop(&self, context: &mut Context) -> jbk::Result<()>;
+    fn on_start(&self, context: &mut Context) -> crate::Result<()>;
+    fn on_stop(&self, context: &mut Context) -> crate::Result<()>;
     fn on_directory_enter(
         &self,
         context: &mut Context,
         entry: &<Builder::Entry as EntryDef>::Dir,
-    ) -> jbk::Result<bool>;
+    ) -> crate::Result<bool>;
     fn on_directory_exit(
         &self,
         context: &mut Context,
         entry: &<Builder::Entry as EntryDef>::Dir,
-    ) -> jbk::Result<()>;
+    ) -> crate::Result<()>;
     fn on_file(
         &self,
         context: &mut Context,
         entry: &<Builder::Entry as EntryDef>::File,
-    ) -> jbk::Result<()>;
+    ) -> crate::Result<()>;
     fn on_link(
         &self,
         context: &mut Context,
         entry: &<Builder::Entry as EntryDef>::Link,
-    ) -> jbk::Result<()>;
+    ) -> crate::Result<()>;
+    /// Called on device nodes, fifos and sockets.
+    ///
+    /// Default implementation ignores them.
+    fn on_special(
+        &self,
+        _context: &mut Context,
+        _entry: &<Builder::Entry as EntryDef>::Special,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
 }
 
 pub struct Walker<'a, Context> {
@@ -37,7 +47,7 @@ impl<'a, Context> Walker<'a, Context> {
         Self { arx, context }
     }
 
-    pub fn run<B>(&mut self, op: &dyn Operator<Context, B>) -> jbk::Result<()>
+    pub fn run<B>(&mut self, op: &dyn Operator<Context, B>) -> crate::Result<()>
     where
         B: FullBuilderTrait,
     {
@@ -52,7 +62,7 @@ impl<'a, Context> Walker<'a, Context> {
         &mut self,
         op: &dyn Operator<Context, B>,
         range: &R,
-    ) -> jbk::Result<()>
+    ) -> crate::Result<()>
     where
         B: FullBuilderTrait,
     {
@@ -68,7 +78,7 @@ impl<'a, Context> Walker<'a, Context> {
         range: &R,
         builder: &RealBuilder<B>,
         op: &dyn Operator<Context, B>,
-    ) -> jbk::Result<()>
+    ) -> crate::Result<()>
     where
         B: FullBuilderTrait,
     {
@@ -77,6 +87,7 @@ impl<'a, Context> Walker<'a, Context> {
             match entry? {
                 Entry::File(e) => op.on_file(&mut self.context, &e)?,
                 Entry::Link(e) => op.on_link(&mut self.context, &e)?,
+                Entry::Special(e) => op.on_special(&mut self.context, &e)?,
                 Entry::Dir(range, e) => {
                     if op.on_directory_enter(&mut self.context, &e)? {
                         self._run(&range, builder, op)?;
@@ -5,10 +5,11 @@ use crate::stream::Stream;
 
 use super::content_address::ContentAddress;
 use super::entry::Entry;
+use crate::error::to_py_err;
 use arx::{FromPathErrorKind, PathBuf};
 use jbk::reader::MayMissPack;
 use pyo3::exceptions::PyRuntimeError;
-use pyo3::exceptions::{PyOSError, PyUnicodeDecodeError, PyValueError};
+use pyo3::exceptions::PyUnicodeDecodeError;
 use pyo3::prelude::*;
 use pyo3::types::PyString;
 
@@ -52,13 +53,16 @@ impl Arx {
         arx: &arx::Arx,
         content: jbk::ContentAddress,
     ) -> PyResult<Stream> {
-        let bytes = arx.container.get_bytes(content).unwrap();
+        let bytes = arx
+            .container
+            .get_bytes(content)
+            .map_err(|e| to_py_err(e.into()))?;
         match bytes {
             MayMissPack::FOUND(bytes) => Ok(Stream(bytes.stream())),
-            MayMissPack::MISSING(pack_info) => Err(PyOSError::new_err(format!(
-                "Cannot found pack {}",
-                pack_info.uuid
-            ))),
+            MayMissPack::MISSING(pack_info) => Err(to_py_err(arx::Error::MissingPack {
+                pack_uuid: pack_info.uuid.to_string(),
+                pack_location: String::from_utf8_lossy(&pack_info.pack_location).into(),
+            })),
         }
     }
 }
@@ -68,11 +72,7 @@ impl Arx {
     #[new]
     fn py_new(path: Bound<PyString>) -> PyResult<Self> {
         let path: std::path::PathBuf = path.extract()?;
-        match arx::Arx::new(path) {
-            Ok(a) => Ok(Self::new(a)),
-
-            Err(_) => Err(PyValueError::new_err("Cannot create arx")),
-        }
+        Ok(Self::new(arx::Arx::new(path).map_err(to_py_err)?))
     }
 
     /// Get an entry for the given path
@@ -87,10 +87,11 @@ impl Arx {
             FromPathErrorKind::BadSeparator => PyRuntimeError::new_err("Invalid path separator"),
             _ => PyRuntimeError::new_err("Unknown error"),
         })?;
-        match self.0.get_entry::<arx::FullBuilder>(&path) {
-            Ok(e) => Ok(Entry::new(Arc::clone(&self.0), e)),
-            Err(_) => Err(PyValueError::new_err("Cannot get entry")),
-        }
+        let entry = self
+            .0
+            .get_entry::<arx::FullBuilder>(&path)
+            .map_err(to_py_err)?;
+        Ok(Entry::new(Arc::clone(&self.0), entry))
     }
 
     /// Get the content associated to contentAddress
@@ -103,10 +104,29 @@ impl Arx {
         Py::new(slf.py(), iter)
     }
 
-    /// Extract the whole archive in
-    #[pyo3(signature=(extract_path=std::path::PathBuf::from(".")))]
-    fn extract(&self, extract_path: std::path::PathBuf) -> PyResult<()> {
-        arx::extract_arx(&self.0, &extract_path, Default::default(), true, false)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    /// Extract the whole archive in extract_path
+    ///
+    /// `include` and `exclude` are lists of glob patterns selecting the entries to extract.
+    /// `*` doesn't match a `/`, use `**` to match across directories.
+    #[pyo3(signature=(extract_path=std::path::PathBuf::from("."), include=vec![], exclude=vec![]))]
+    fn extract(
+        &self,
+        extract_path: std::path::PathBuf,
+        include: Vec<String>,
+        exclude: Vec<String>,
+    ) -> PyResult<()> {
+        let options = arx::ExtractOptions {
+            filter: arx::PathFilter::new(&include, &exclude).map_err(to_py_err)?,
+            ..Default::default()
+        };
+        arx::extract_arx(
+            &self.0,
+            &extract_path,
+            Default::default(),
+            true,
+            false,
+            &options,
+        )
+        .map_err(to_py_err)
     }
 }
@@ -1,6 +1,7 @@
 use std::rc::Rc;
 use std::{path::PathBuf, sync::Arc};
 
+use crate::error::to_py_err;
 use arx::create::{FsAdder, SimpleCreator};
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
@@ -33,7 +34,7 @@ impl Creator {
                     Rc::new(()),
                     jbk::creator::Compression::zstd(),
                 )
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?,
+                .map_err(to_py_err)?,
             ),
             outfile,
         })
@@ -62,9 +63,7 @@ impl Creator {
         slf.started = false;
         match slf.creator.take() {
             None => Err(PyRuntimeError::new_err("Creator already finalized")),
-            Some(creator) => creator
-                .finalize(&slf.outfile)
-                .map_err(|e| PyRuntimeError::new_err(e.to_string())),
+            Some(creator) => creator.finalize(&slf.outfile).map_err(to_py_err),
         }
     }
 
@@ -81,9 +80,7 @@ impl Creator {
                     ));
                 }
                 let mut adder = FsAdder::new(creator, "".into());
-                adder
-                    .add_from_path(path, recursive)
-                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))
+                adder.add_from_path(path, recursive).map_err(to_py_err)
             }
         }
     }
@@ -1,16 +1,15 @@
 use std::{borrow::Cow, sync::Arc};
 
 use arx::CommonEntry;
-use pyo3::{
-    exceptions::{PyRuntimeError, PyTypeError},
-    prelude::*,
-};
+use pyo3::{exceptions::PyTypeError, prelude::*};
 
-use crate::{content_address::ContentAddress, iterator::EntryIter, stream::Stream};
+use crate::{
+    content_address::ContentAddress, error::to_py_err, iterator::EntryIter, stream::Stream,
+};
 
 /// An entry i an arx archive.
 ///
-/// Can be a directory, a file or a link.
+/// Can be a directory, a file, a link or a special file (device, fifo or socket).
 #[pyclass]
 pub struct Entry {
     arx: Arc<arx::Arx>,
@@ -36,6 +35,9 @@ impl Entry {
             arx::Entry::Dir(_, e) => {
                 format!("Dir({})", String::from_utf8(e.path().clone()).unwrap())
             }
+            arx::Entry::Special(e) => {
+                format!("Special({})", String::from_utf8(e.path().clone()).unwrap())
+            }
         }
     }
 
@@ -46,6 +48,7 @@ impl Entry {
             arx::Entry::File(e) => e.idx().into_u32(),
             arx::Entry::Link(e) => e.idx().into_u32(),
             arx::Entry::Dir(_, e) => e.idx().into_u32(),
+            arx::Entry::Special(e) => e.idx().into_u32(),
         }
     }
 
@@ -56,6 +59,7 @@ impl Entry {
             arx::Entry::File(e) => String::from_utf8(e.path().clone()).unwrap(),
             arx::Entry::Link(e) => String::from_utf8(e.path().clone()).unwrap(),
             arx::Entry::Dir(_, e) => String::from_utf8(e.path().clone()).unwrap(),
+            arx::Entry::Special(e) => String::from_utf8(e.path().clone()).unwrap(),
         })
     }
 
@@ -66,6 +70,7 @@ impl Entry {
             arx::Entry::File(e) => e.parent(),
             arx::Entry::Link(e) => e.parent(),
             arx::Entry::Dir(_, e) => e.parent(),
+            arx::Entry::Special(e) => e.parent(),
         };
         match parent_idx {
             None => Ok(None),
@@ -73,7 +78,7 @@ impl Entry {
                 let arx_entry = self
                     .arx
                     .get_entry_at_idx::<arx::FullBuilder>(i)
-                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                    .map_err(to_py_err)?;
                 let entry = Entry::new(self.arx.clone(), arx_entry);
                 Ok(Some(entry))
             }
@@ -87,6 +92,7 @@ impl Entry {
             arx::Entry::File(e) => e.owner(),
             arx::Entry::Link(e) => e.owner(),
             arx::Entry::Dir(_, e) => e.owner(),
+            arx::Entry::Special(e) => e.owner(),
         }
     }
 
@@ -97,16 +103,20 @@ impl Entry {
             arx::Entry::File(e) => e.group(),
             arx::Entry::Link(e) => e.group(),
             arx::Entry::Dir(_, e) => e.group(),
+            arx::Entry::Special(e) => e.group(),
         }
     }
 
     /// The rigths (int) of the entry.
+    ///
+    /// This includes the setuid, setgid and sticky bits.
     #[getter]
-    fn rights(&self) -> u8 {
+    fn rights(&self) -> u16 {
         match &self.entry {
             arx::Entry::File(e) => e.rights(),
             arx::Entry::Link(e) => e.rights(),
             arx::Entry::Dir(_, e) => e.rights(),
+            arx::Entry::Special(e) => e.rights(),
         }
     }
 
@@ -117,6 +127,51 @@ impl Entry {
             arx::Entry::File(e) => e.mtime(),
             arx::Entry::Link(e) => e.mtime(),
             arx::Entry::Dir(_, e) => e.mtime(),
+            arx::Entry::Special(e) => e.mtime(),
+        }
+    }
+
+    /// The sub-second part (in nanoseconds) of the modification time.
+    #[getter]
+    fn mtime_nsec(&self) -> u32 {
+        match &self.entry {
+            arx::Entry::File(e) => e.mtime_nsec(),
+            arx::Entry::Link(e) => e.mtime_nsec(),
+            arx::Entry::Dir(_, e) => e.mtime_nsec(),
+            arx::Entry::Special(e) => e.mtime_nsec(),
+        }
+    }
+
+    /// The access time (in nanoseconds since epoch) of the entry, or None if unknown.
+    #[getter]
+    fn atime_ns(&self) -> Option<u64> {
+        match &self.entry {
+            arx::Entry::File(e) => e.atime_ns(),
+            arx::Entry::Link(e) => e.atime_ns(),
+            arx::Entry::Dir(_, e) => e.atime_ns(),
+            arx::Entry::Special(e) => e.atime_ns(),
+        }
+    }
+
+    /// The status change time (in nanoseconds since epoch) of the entry, or None if unknown.
+    #[getter]
+    fn ctime_ns(&self) -> Option<u64> {
+        match &self.entry {
+            arx::Entry::File(e) => e.ctime_ns(),
+            arx::Entry::Link(e) => e.ctime_ns(),
+            arx::Entry::Dir(_, e) => e.ctime_ns(),
+            arx::Entry::Special(e) => e.ctime_ns(),
+        }
+    }
+
+    /// The creation (birth) time (in nanoseconds since epoch) of the entry, or None if unknown.
+    #[getter]
+    fn btime_ns(&self) -> Option<u64> {
+        match &self.entry {
+            arx::Entry::File(e) => e.btime_ns(),
+            arx::Entry::Link(e) => e.btime_ns(),
+            arx::Entry::Dir(_, e) => e.btime_ns(),
+            arx::Entry::Special(e) => e.btime_ns(),
         }
     }
 
@@ -135,6 +190,11 @@ impl Entry {
         matches!(&self.entry, arx::Entry::Dir(_, _))
     }
 
+    /// Return True if the entry is a special entry (device, fifo or socket)
+    fn is_special(&self) -> bool {
+        matches!(&self.entry, arx::Entry::Special(_))
+    }
+
     /// Get the content address of the file entry.
     ///
     /// Raise an exception if entry is not a file.
@@ -0,0 +1,19 @@
+use pyo3::exceptions::{
+    PyFileExistsError, PyFileNotFoundError, PyNotADirectoryError, PyOSError, PyRuntimeError,
+    PyValueError,
+};
+use pyo3::PyErr;
+
+/// Convert an arx error into the corresponding python exception.
+pub(crate) fn to_py_err(error: arx::Error) -> PyErr {
+    match error {
+        arx::Error::NotFound(path) => PyFileNotFoundError::new_err(path.to_string()),
+        arx::Error::NotADirectory(path) => PyNotADirectoryError::new_err(path.to_string()),
+        arx::Error::AlreadyExists(path) => PyFileExistsError::new_err(path.to_string()),
+        arx::Error::InvalidPath(msg) => PyValueError::new_err(msg),
+        arx::Error::MissingPack { .. } => PyOSError::new_err(error.to_string()),
+        arx::Error::Io(e) => e.into(),
+        arx::Error::Format(e) => PyRuntimeError::new_err(e.to_string()),
+        arx::Error::Extract(_) => PyOSError::new_err(error.to_string()),
+    }
+}
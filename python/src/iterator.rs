@@ -1,11 +1,12 @@
 use std::sync::Arc;
 
 use super::entry::Entry;
+use crate::error::to_py_err;
 use jbk::{
     reader::{Index, Range},
     EntryIdx, EntryRange,
 };
-use pyo3::{exceptions::PyRuntimeError, prelude::*};
+use pyo3::prelude::*;
 
 #[pyclass]
 pub struct EntryIter {
@@ -44,7 +45,7 @@ impl EntryIter {
             Arc::clone(&slf.arx),
             slf.arx
                 .get_entry_at_idx::<arx::FullBuilder>(slf.start)
-                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?,
+                .map_err(to_py_err)?,
         );
         slf.start += 1;
         Ok(Some(ret))
@@ -2,6 +2,7 @@ mod arx;
 mod content_address;
 mod creator;
 mod entry;
+mod error;
 mod iterator;
 mod stream;
 use pyo3::prelude::*;
@@ -0,0 +1,19 @@
+import pytest
+
+import libarx
+
+
+def test_open_missing_archive(tmp_path):
+    with pytest.raises(FileNotFoundError):
+        libarx.Arx(str(tmp_path / "missing.arx"))
+
+
+def test_get_missing_entry(tmp_path, monkeypatch):
+    monkeypatch.chdir(tmp_path)
+    (tmp_path / "file.txt").write_text("Some content")
+    with libarx.Creator("test.arx") as creator:
+        creator.add("file.txt")
+
+    arx = libarx.Arx("test.arx")
+    with pytest.raises(FileNotFoundError):
+        arx.get_entry("missing.txt")
@@ -1,8 +1,10 @@
-use bstr::{BString, ByteVec};
+use bstr::{BString, ByteSlice};
 use clap::{CommandFactory, Parser, ValueHint};
 
 use anyhow::{anyhow, Result};
 use jbk::creator::ContentAdder;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -55,6 +57,27 @@ struct Cli {
     #[arg(long, default_value_t = false, action)]
     list_compressions: bool,
 
+    /// Compute and store a checksum of the content of each file.
+    #[arg(long, default_value_t = false, action)]
+    checksum: bool,
+
+    /// Size above which a tar member is written in a temporary file before being added.
+    ///
+    /// Smaller members are read in memory. This is not a bound on the memory used: besides
+    /// the member being added, the creator keeps its own buffers.
+    /// Accept the K, M and G suffixes.
+    #[arg(long, value_parser=arx::cmd_utils::size_arg_parser, default_value = "64M")]
+    spill_threshold: u64,
+
+    /// How to convert paths which are not valid utf-8.
+    #[arg(long, value_enum, default_value_t = NonUtf8::Error)]
+    non_utf8: NonUtf8,
+
+    /// Use the owner and group names (when they exist on this system) instead of the ids
+    /// stored in the tar.
+    #[arg(long, default_value_t = false, action)]
+    owner_names: bool,
+
     #[arg(long, help_heading = "Advanced")]
     generate_man_page: bool,
 
@@ -127,9 +150,65 @@ impl jbk::creator::Progress for ProgressBar {
     fn content_added(&self, _size: jbk::Size) {}
 }
 
+/// How to handle names (paths and hard link targets) which are not valid utf-8.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+enum NonUtf8 {
+    /// Stop the conversion with an error.
+    #[default]
+    Error,
+    /// Replace invalid sequences with the replacement character (U+FFFD).
+    Lossy,
+    /// Replace invalid bytes with their hexadecimal value (`%XX`).
+    Escape,
+}
+
+impl NonUtf8 {
+    fn convert(self, name: &[u8]) -> jbk::Result<arx::PathBuf> {
+        let name = match std::str::from_utf8(name) {
+            Ok(name) => Cow::Borrowed(name),
+            Err(_) => match self {
+                Self::Error => {
+                    return Err(format!(
+                        "{} is not valid utf-8 (see option --non-utf8)",
+                        name.as_bstr()
+                    )
+                    .into())
+                }
+                Self::Lossy => String::from_utf8_lossy(name),
+                Self::Escape => {
+                    let mut escaped = String::new();
+                    for chunk in name.utf8_chunks() {
+                        escaped.push_str(chunk.valid());
+                        for byte in chunk.invalid() {
+                            escaped.push_str(&format!("%{byte:02X}"));
+                        }
+                    }
+                    Cow::Owned(escaped)
+                }
+            },
+        };
+        arx::PathBuf::from_path(Path::new(name.as_ref()))
+            .map_err(|e| format!("Invalid path {name}: {e}").into())
+    }
+}
+
+/// Options of the conversion.
+#[derive(Clone, Debug)]
+pub struct ConvertOptions {
+    checksum: bool,
+    spill_threshold: u64,
+    non_utf8: NonUtf8,
+    owner_names: bool,
+}
+
 pub struct Converter<R: Read> {
     arx_creator: arx::create::SimpleCreator,
     archive: tar::Archive<R>,
+    options: ConvertOptions,
+    // Metadata of the last PAX global header, applying to all following entries.
+    global_pax: PaxInfo,
+    // Kind of the symlinks and special files already added, for the hard links to them.
+    non_regular: HashMap<arx::PathBuf, arx::create::EntryKind>,
 }
 
 struct TarEntry {
@@ -139,85 +218,216 @@ struct TarEntry {
     gid: u64,
     mode: u64,
     mtime: u64,
+    mtime_nsec: u32,
+    atime_ns: Option<u64>,
+    ctime_ns: Option<u64>,
+    checksum: Option<[u8; 32]>,
+    xattrs: arx::Xattrs,
+}
+
+/// Parse a PAX time ("<secs>[.<frac>]") into nanoseconds since epoch.
+///
+/// Times before epoch are not supported and return None.
+fn parse_pax_time(value: &[u8]) -> Option<u64> {
+    let value = std::str::from_utf8(value).ok()?;
+    let (secs, frac) = value.split_once('.').unwrap_or((value, ""));
+    let secs: u64 = secs.parse().ok()?;
+    // We keep only nanoseconds precision.
+    let frac = &frac[..frac.len().min(9)];
+    let nsec = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<u64>().ok()? * 10u64.pow(9 - frac.len() as u32)
+    };
+    secs.checked_mul(1_000_000_000)?.checked_add(nsec)
+}
+
+/// Parse the records ("<len> <key>=<value>\n") of a PAX header.
+fn parse_pax_records(mut data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut records = vec![];
+    while let Some(space) = data.iter().position(|b| *b == b' ') {
+        let len = std::str::from_utf8(&data[..space])
+            .ok()
+            .and_then(|l| l.parse::<usize>().ok());
+        let len = match len {
+            Some(len) if len > space + 1 && len <= data.len() && data[len - 1] == b'\n' => len,
+            _ => break,
+        };
+        // Record ends with a new line.
+        let record = &data[space + 1..len - 1];
+        if let Some(equal) = record.iter().position(|b| *b == b'=') {
+            records.push((&record[..equal], &record[equal + 1..]));
+        }
+        data = &data[len..];
+    }
+    records
+}
+
+/// Metadata read from PAX headers.
+#[derive(Clone, Debug, Default)]
+struct PaxInfo {
+    mtime: Option<u64>,
+    atime: Option<u64>,
+    ctime: Option<u64>,
+    uid: Option<u64>,
+    gid: Option<u64>,
+    uname: Option<Vec<u8>>,
+    gname: Option<Vec<u8>>,
+    xattrs: arx::Xattrs,
+}
+
+impl PaxInfo {
+    fn apply(&mut self, key: &[u8], value: &[u8]) {
+        let parse_id = |value: &[u8]| std::str::from_utf8(value).ok()?.parse().ok();
+        match key {
+            b"mtime" => self.mtime = parse_pax_time(value),
+            b"atime" => self.atime = parse_pax_time(value),
+            b"ctime" => self.ctime = parse_pax_time(value),
+            b"uid" => self.uid = parse_id(value),
+            b"gid" => self.gid = parse_id(value),
+            b"uname" => self.uname = Some(value.to_vec()),
+            b"gname" => self.gname = Some(value.to_vec()),
+            key => {
+                if let Some(name) = key.strip_prefix(b"SCHILY.xattr.") {
+                    self.xattrs.insert(name, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn lookup_user(name: &[u8]) -> Option<u64> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+    (!passwd.is_null()).then(|| unsafe { (*passwd).pw_uid } as u64)
+}
+
+#[cfg(unix)]
+fn lookup_group(name: &[u8]) -> Option<u64> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let group = unsafe { libc::getgrnam(name.as_ptr()) };
+    (!group.is_null()).then(|| unsafe { (*group).gr_gid } as u64)
+}
+
+#[cfg(windows)]
+fn lookup_user(_name: &[u8]) -> Option<u64> {
+    None
+}
+
+#[cfg(windows)]
+fn lookup_group(_name: &[u8]) -> Option<u64> {
+    None
 }
 
 impl TarEntry {
     pub fn new<'a, R: 'a + Read>(
         mut entry: tar::Entry<'a, R>,
         adder: &mut impl ContentAdder,
+        global_pax: &PaxInfo,
+        options: &ConvertOptions,
     ) -> jbk::Result<Option<Self>> {
+        let mut pax = global_pax.clone();
+        if let Some(extensions) = entry.pax_extensions()? {
+            for extension in extensions {
+                let extension = extension?;
+                pax.apply(extension.key_bytes(), extension.value_bytes());
+            }
+        }
         let header = entry.header();
-        let uid = header.uid()?;
-        let gid = header.gid()?;
-        let mtime = header.mtime()?;
+        let mut uid = pax.uid.map_or_else(|| header.uid(), Ok)?;
+        let mut gid = pax.gid.map_or_else(|| header.gid(), Ok)?;
+        if options.owner_names {
+            let uname = pax.uname.as_deref().or(header.username_bytes());
+            if let Some(id) = uname.and_then(lookup_user) {
+                uid = id;
+            }
+            let gname = pax.gname.as_deref().or(header.groupname_bytes());
+            if let Some(id) = gname.and_then(lookup_group) {
+                gid = id;
+            }
+        }
+        // PAX mtime is more precise than the header one.
+        let (mtime, mtime_nsec) = match pax.mtime {
+            Some(t) => (t / 1_000_000_000, (t % 1_000_000_000) as u32),
+            None => (header.mtime()?, 0),
+        };
         let mode = header.mode()? as u64;
-        let path = arx::PathBuf::from_path(entry.path()?)
-            .unwrap_or_else(|_| panic!("Entry path must be utf-8"));
-        Ok(match header.entry_type() {
-            tar::EntryType::Directory => Some(Self {
-                path,
-                kind: arx::create::EntryKind::Dir,
-                uid,
-                gid,
-                mtime,
-                mode,
-            }),
+        let entry_type = header.entry_type();
+        let is_old_dir = header.as_ustar().is_none() && header.path_bytes().ends_with(b"/");
+        let path = options.non_utf8.convert(&entry.path_bytes())?;
+        let mut checksum = None;
+        let kind = match entry_type {
+            tar::EntryType::Directory => arx::create::EntryKind::Dir,
             tar::EntryType::Symlink => {
-                let target = entry.link_name()?.unwrap();
-                Some(Self {
-                    path,
-                    kind: arx::create::EntryKind::Link(BString::new(
-                        Vec::from_path_buf(target.into_owned())
-                            .unwrap_or_else(|target| panic!("{target:?} must be utf8")),
-                    )),
-                    uid,
-                    gid,
-                    mtime,
-                    mode,
-                })
+                let target = entry
+                    .link_name_bytes()
+                    .ok_or_else(|| format!("Symbolic link {path} has no target"))?;
+                arx::create::EntryKind::Link(BString::from(target.into_owned()))
+            }
+            tar::EntryType::Link => {
+                let target = entry
+                    .link_name_bytes()
+                    .ok_or_else(|| format!("Hard link {path} has no target"))?;
+                arx::create::EntryKind::HardLink(options.non_utf8.convert(&target)?)
+            }
+            tar::EntryType::Char | tar::EntryType::Block | tar::EntryType::Fifo => {
+                let kind = match entry_type {
+                    tar::EntryType::Char => arx::SpecialKind::CharDevice,
+                    tar::EntryType::Block => arx::SpecialKind::BlockDevice,
+                    _ => arx::SpecialKind::Fifo,
+                };
+                let major = header.device_major()?.unwrap_or(0);
+                let minor = header.device_minor()?.unwrap_or(0);
+                arx::create::EntryKind::Special(kind, major, minor)
             }
             /* GNULongName, GNULongLink and XHeader should already be handled by entries iterator
                but it doesn't arm to explicitly ignore them.
-               XGlobalHeader is not handled by entries iterator, so we MUST explicitly ignore it.
+               XGlobalHeader is handled by the converter.
             */
             tar::EntryType::GNULongName
             | tar::EntryType::GNULongLink
             | tar::EntryType::XHeader
-            | tar::EntryType::XGlobalHeader => None,
-            _ => {
-                if header.as_ustar().is_none() && header.path_bytes().ends_with(b"/") {
-                    Some(Self {
-                        path,
-                        kind: arx::create::EntryKind::Dir,
-                        uid,
-                        gid,
-                        mtime,
-                        mode,
-                    })
-                } else {
-                    //Handle everything else as normal file
-                    let mut data = vec![];
-                    let size = entry.read_to_end(&mut data)?;
-                    let content_address = adder.add_content(
-                        Box::new(std::io::Cursor::new(data)),
-                        jbk::creator::CompHint::Detect,
-                    )?;
-                    Some(Self {
-                        path,
-                        kind: arx::create::EntryKind::File(size.into(), content_address),
-                        uid,
-                        gid,
-                        mtime,
-                        mode,
-                    })
-                }
+            | tar::EntryType::XGlobalHeader => return Ok(None),
+            // Old tar format stores directories as regular files with a trailing slash.
+            tar::EntryType::Regular if is_old_dir => arx::create::EntryKind::Dir,
+            tar::EntryType::Regular | tar::EntryType::Continuous | tar::EntryType::GNUSparse => {
+                let size = entry.size();
+                let (content, size, file_checksum) = arx::create::read_content(
+                    &mut entry,
+                    size,
+                    options.spill_threshold,
+                    options.checksum,
+                )?;
+                checksum = file_checksum;
+                let content_address = adder.add_content(content, jbk::creator::CompHint::Detect)?;
+                arx::create::EntryKind::File(size.into(), content_address)
             }
-        })
+            other => {
+                eprintln!(
+                    "Warning: {path} has an unsupported type ({other:?}), it is not converted"
+                );
+                return Ok(None);
+            }
+        };
+        Ok(Some(Self {
+            path,
+            kind,
+            uid,
+            gid,
+            mode,
+            mtime,
+            mtime_nsec,
+            atime_ns: pax.atime,
+            ctime_ns: pax.ctime,
+            checksum,
+            xattrs: pax.xattrs,
+        }))
     }
 }
 
 impl arx::create::EntryTrait for TarEntry {
-    fn kind(&self) -> jbk::Result<Option<arx::create::EntryKind>> {
+    fn kind(&self) -> arx::Result<Option<arx::create::EntryKind>> {
         Ok(Some(self.kind.clone()))
     }
     fn path(&self) -> &arx::Path {
@@ -236,6 +446,21 @@ impl arx::create::EntryTrait for TarEntry {
     fn mtime(&self) -> u64 {
         self.mtime
     }
+    fn mtime_nsec(&self) -> u32 {
+        self.mtime_nsec
+    }
+    fn atime_ns(&self) -> Option<u64> {
+        self.atime_ns
+    }
+    fn ctime_ns(&self) -> Option<u64> {
+        self.ctime_ns
+    }
+    fn checksum(&self) -> Option<[u8; 32]> {
+        self.checksum
+    }
+    fn xattrs(&self) -> Option<&arx::Xattrs> {
+        Some(&self.xattrs)
+    }
 }
 
 impl<R: Read> Converter<R> {
@@ -245,6 +470,7 @@ impl<R: Read> Converter<R> {
         concat_mode: jbk::creator::ConcatMode,
         compression: jbk::creator::Compression,
         progress_bar: indicatif::ProgressBar,
+        options: ConvertOptions,
     ) -> jbk::Result<Self> {
         let progress = Arc::new(ProgressBar::new(progress_bar)?);
         let arx_creator = arx::create::SimpleCreator::new(
@@ -258,22 +484,52 @@ impl<R: Read> Converter<R> {
         Ok(Self {
             arx_creator,
             archive,
+            options,
+            global_pax: Default::default(),
+            non_regular: Default::default(),
         })
     }
 
-    fn finalize(self, outfile: &Path) -> jbk::Result<()> {
+    fn finalize(self, outfile: &Path) -> arx::Result<()> {
         self.arx_creator.finalize(outfile)
     }
 
     pub fn run(mut self, outfile: &Path) -> jbk::Result<()> {
         let iter = self.archive.entries()?;
         for entry in iter {
-            let entry = entry?;
-            if let Some(entry) = TarEntry::new(entry, self.arx_creator.adder())? {
+            let mut entry = entry?;
+            if entry.header().entry_type() == tar::EntryType::XGlobalHeader {
+                let mut data = vec![];
+                entry.read_to_end(&mut data)?;
+                for (key, value) in parse_pax_records(&data) {
+                    self.global_pax.apply(key, value);
+                }
+                continue;
+            }
+            if let Some(mut entry) = TarEntry::new(
+                entry,
+                self.arx_creator.adder(),
+                &self.global_pax,
+                &self.options,
+            )? {
+                // Arx hard links can only point to regular files.
+                // A hard link to a symlink or a special file is stored as a copy of its target.
+                if let arx::create::EntryKind::HardLink(target) = &entry.kind {
+                    if let Some(kind) = self.non_regular.get(target) {
+                        entry.kind = kind.clone();
+                    }
+                }
+                if matches!(
+                    entry.kind,
+                    arx::create::EntryKind::Link(_) | arx::create::EntryKind::Special(..)
+                ) {
+                    self.non_regular
+                        .insert(entry.path.clone(), entry.kind.clone());
+                }
                 self.arx_creator.add_entry(&entry)?;
             }
         }
-        self.finalize(outfile)
+        Ok(self.finalize(outfile)?)
     }
 }
 
@@ -361,6 +617,135 @@ fn main() -> Result<()> {
         },
         args.compression,
         progress_bar,
+        ConvertOptions {
+            checksum: args.checksum,
+            spill_threshold: args.spill_threshold,
+            non_utf8: args.non_utf8,
+            owner_names: args.owner_names,
+        },
     )?;
     Ok(converter.run(&outfile)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arx::CommonEntry;
+
+    #[test]
+    fn test_parse_pax_records() {
+        let data = b"30 mtime=1000000000.123456789\n12 uid=1234\n20 SCHILY.xattr.a=b\n";
+        assert_eq!(
+            parse_pax_records(data),
+            vec![
+                (b"mtime".as_slice(), b"1000000000.123456789".as_slice()),
+                (b"uid".as_slice(), b"1234".as_slice()),
+                (b"SCHILY.xattr.a".as_slice(), b"b".as_slice())
+            ]
+        );
+        // Values may contain spaces, `=` and new lines.
+        assert_eq!(
+            parse_pax_records(b"16 path=a b=c\nd\n"),
+            vec![(b"path".as_slice(), b"a b=c\nd".as_slice())]
+        );
+        // Records without `=` are ignored.
+        assert_eq!(
+            parse_pax_records(b"8 uid12\n12 uid=1234\n"),
+            vec![(b"uid".as_slice(), b"1234".as_slice())]
+        );
+        assert!(parse_pax_records(b"").is_empty());
+    }
+
+    #[test]
+    fn test_parse_pax_records_malformed() {
+        // Parsing stops at the first malformed record.
+        let valid = b"12 uid=1234\n".as_slice();
+        for (reason, malformed) in [
+            ("no length", b"gid=12\n".as_slice()),
+            ("not a number", b"ab gid=12\n".as_slice()),
+            ("negative length", b"-9 gid=12\n".as_slice()),
+            (
+                "overflowing length",
+                b"99999999999999999999999 gid=12\n".as_slice(),
+            ),
+            ("shorter than the length prefix", b"3 gid=12\n".as_slice()),
+            ("empty record", b"2 ".as_slice()),
+            ("too long", b"11 gid=12\n".as_slice()),
+            ("too short", b"8 gid=12\n".as_slice()),
+        ] {
+            let data = [valid, malformed, valid].concat();
+            assert_eq!(
+                parse_pax_records(&data),
+                vec![(b"uid".as_slice(), b"1234".as_slice())],
+                "{reason}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_pax_time() {
+        assert_eq!(parse_pax_time(b"1000"), Some(1_000_000_000_000));
+        assert_eq!(parse_pax_time(b"1000.5"), Some(1_000_500_000_000));
+        assert_eq!(parse_pax_time(b"1000.1234567891"), Some(1_000_123_456_789));
+        assert_eq!(parse_pax_time(b"-1"), None);
+        assert_eq!(parse_pax_time(b"1000.x"), None);
+        assert_eq!(parse_pax_time(b"100000000000"), None);
+    }
+
+    /// Hard links to symlinks are converted as symlinks instead of aborting the conversion.
+    #[test]
+    fn test_hard_link_to_symlink() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(0);
+        header.set_mode(0o777);
+        header.set_mtime(1_000_000_000);
+        header.set_entry_type(tar::EntryType::Symlink);
+        builder
+            .append_link(&mut header, "link", "file.txt")
+            .unwrap();
+        header.set_entry_type(tar::EntryType::Link);
+        builder
+            .append_link(&mut header, "hard_link", "link")
+            .unwrap();
+        // A hard link to the hard link must also be a symlink.
+        builder
+            .append_link(&mut header, "hard_link2", "hard_link")
+            .unwrap();
+        let data = builder.into_inner().unwrap();
+
+        let outfile = tmp_dir.path().join("test.arx");
+        Converter::new(
+            tar::Archive::new(std::io::Cursor::new(data)),
+            &outfile,
+            jbk::creator::ConcatMode::OneFile,
+            jbk::creator::Compression::None,
+            indicatif::ProgressBar::hidden(),
+            ConvertOptions {
+                checksum: false,
+                spill_threshold: 1024,
+                non_utf8: Default::default(),
+                owner_names: false,
+            },
+        )
+        .unwrap()
+        .run(&outfile)
+        .unwrap();
+
+        let arx = arx::Arx::new(&outfile).unwrap();
+        for path in ["link", "hard_link", "hard_link2"] {
+            match arx
+                .get_entry::<arx::FullBuilder>(arx::Path::new(path))
+                .unwrap()
+            {
+                arx::Entry::Link(link) => {
+                    assert_eq!(link.target().as_slice(), b"file.txt", "{path}");
+                    assert_eq!(link.mtime(), 1_000_000_000, "{path}");
+                }
+                _ => panic!("{path} must be a link"),
+            }
+        }
+    }
+}
@@ -60,6 +60,19 @@ struct Cli {
     #[arg(long, default_value_t = false, action)]
     list_compressions: bool,
 
+    /// Compute and store a checksum of the content of each file.
+    #[arg(long, default_value_t = false, action)]
+    checksum: bool,
+
+    /// Size above which a compressed zip entry is decompressed in a temporary file before
+    /// being added.
+    ///
+    /// Smaller entries are decompressed in memory. This is not a bound on the memory used:
+    /// besides the entry being added, the creator keeps its own buffers.
+    /// Accept the K, M and G suffixes.
+    #[arg(long, value_parser=arx::cmd_utils::size_arg_parser, default_value = "64M")]
+    spill_threshold: u64,
+
     #[arg(long, help_heading = "Advanced")]
     generate_man_page: bool,
 
@@ -128,18 +141,97 @@ impl jbk::creator::Progress for ProgressBar {
     }
 }
 
+/// Mask of the file type bits of a unix mode.
+const S_IFMT: u32 = 0o170000;
+
+/// File type bits of a symbolic link.
+const S_IFLNK: u32 = 0o120000;
+
+/// Header id of the Info-ZIP unix extra field (uid/gid of any size).
+const INFOZIP_UNIX_ID: u16 = 0x7875;
+
+/// Header id of the old Info-ZIP unix extra field (16 bits uid/gid).
+const INFOZIP_UNIX_OLD_ID: u16 = 0x7855;
+
+/// Read a little endian unsigned integer of (at most) 8 bytes.
+fn read_le(data: &[u8]) -> Option<u64> {
+    if data.len() > 8 {
+        return None;
+    }
+    Some(
+        data.iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | *byte as u64),
+    )
+}
+
+/// Parse the owner from the Info-ZIP unix extra field (0x7875)
+/// or, if absent, the old one (0x7855).
+///
+/// `zip` doesn't parse those fields, so we read the raw extra data.
+fn parse_owner(mut extra_data: &[u8]) -> Option<(u64, u64)> {
+    let mut old_owner = None;
+    while extra_data.len() >= 4 {
+        let id = u16::from_le_bytes([extra_data[0], extra_data[1]]);
+        let len = u16::from_le_bytes([extra_data[2], extra_data[3]]) as usize;
+        let data = match extra_data.get(4..4 + len) {
+            Some(data) => data,
+            // Truncated field, ignore it (and the following data).
+            None => break,
+        };
+        extra_data = &extra_data[4 + len..];
+        match id {
+            INFOZIP_UNIX_ID => {
+                if let Some(owner) = parse_infozip_unix(data) {
+                    return Some(owner);
+                }
+            }
+            // Only present (with uid/gid) in the local header.
+            INFOZIP_UNIX_OLD_ID if data.len() >= 4 => {
+                let uid = u16::from_le_bytes([data[0], data[1]]);
+                let gid = u16::from_le_bytes([data[2], data[3]]);
+                old_owner = Some((uid as u64, gid as u64));
+            }
+            _ => {}
+        }
+    }
+    old_owner
+}
+
+/// Parse the data of the Info-ZIP unix extra field:
+/// version (1), uid size, uid, gid size, gid.
+fn parse_infozip_unix(data: &[u8]) -> Option<(u64, u64)> {
+    if data.first() != Some(&1) {
+        return None;
+    }
+    let uid_size = *data.get(1)? as usize;
+    let uid = read_le(data.get(2..2 + uid_size)?)?;
+    let gid_size = *data.get(2 + uid_size)? as usize;
+    let gid = read_le(data.get(3 + uid_size..3 + uid_size + gid_size)?)?;
+    Some((uid, gid))
+}
+
+pub struct ConvertOptions {
+    checksum: bool,
+    spill_threshold: u64,
+}
+
 pub struct Converter<R: Read + Seek> {
     arx_creator: arx::create::SimpleCreator,
     archive_path: PathBuf,
     archive: zip::ZipArchive<R>,
     progress: Arc<ProgressBar>,
+    options: ConvertOptions,
 }
 
 struct ZipEntry {
     path: arx::PathBuf,
     kind: arx::create::EntryKind,
+    uid: u64,
+    gid: u64,
     mode: u64,
     mtime: u64,
+    checksum: Option<[u8; 32]>,
 }
 
 impl ZipEntry {
@@ -147,12 +239,14 @@ impl ZipEntry {
         mut entry: zip::read::ZipFile<'_>,
         adder: &mut impl ContentAdder,
         archive_path: &Path,
+        options: &ConvertOptions,
     ) -> jbk::Result<Self> {
         let mut mtime = None;
         for extra_field in entry.extra_data_fields() {
             match extra_field {
                 zip::ExtraField::ExtendedTimestamp(ex_timestamp) => {
-                    mtime = ex_timestamp.mod_time().map(|ts| ts as u64)
+                    // The extended timestamp is signed, times before 1970 are negative.
+                    mtime = ex_timestamp.mod_time().map(|ts| ts as i32 as i64 as u64)
                 }
             }
         }
@@ -160,27 +254,37 @@ impl ZipEntry {
             Some(ts) => ts,
             None => entry
                 .last_modified()
-                .map(|ts| time::OffsetDateTime::try_from(ts).unwrap().unix_timestamp() as u64)
+                // Invalid dos dates (out of range month or day) are ignored.
+                .and_then(|ts| time::OffsetDateTime::try_from(ts).ok())
+                .map(|ts| ts.unix_timestamp() as u64)
                 .unwrap_or(0),
         };
-        let mode = entry.unix_mode().unwrap_or(0o644) as u64;
-        let path = entry.enclosed_name();
-        if path.is_none() {
-            return Err("Invalid path".into());
-        }
-        let path = path.unwrap();
-        let path =
-            arx::PathBuf::from_path(&path).unwrap_or_else(|_| panic!("{path:?} must be utf8"));
-
-        Ok(if entry.is_dir() {
-            Self {
-                path,
-                kind: arx::create::EntryKind::Dir,
-                mtime,
-                mode,
-            }
+        let unix_mode = entry.unix_mode();
+        let mode = unix_mode.unwrap_or(0o644) as u64;
+        let (uid, gid) = entry.extra_data().and_then(parse_owner).unwrap_or((0, 0));
+        let path = match entry.enclosed_name() {
+            Some(path) => path,
+            None => return Err(format!("Invalid path {:?}", entry.name()).into()),
+        };
+        let path = arx::PathBuf::from_path(&path)
+            .map_err(|_| format!("{} is not a valid utf-8 path", path.display()))?;
+
+        let mut checksum = None;
+        let kind = if entry.is_dir() {
+            arx::create::EntryKind::Dir
+        } else if unix_mode.is_some_and(|m| m & S_IFMT == S_IFLNK) {
+            // Symlinks are stored as a entry containing the target.
+            let mut target = vec![];
+            entry.read_to_end(&mut target)?;
+            arx::create::EntryKind::Link(target.into())
         } else {
+            let size = entry.size();
             let content_address = if let zip::CompressionMethod::Stored = entry.compression() {
+                if options.checksum {
+                    let mut hasher = blake3::Hasher::new();
+                    std::io::copy(&mut entry, &mut hasher)?;
+                    checksum = Some(*hasher.finalize().as_bytes());
+                }
                 let reader = jbk::creator::InputFile::new_range(
                     std::fs::File::open(archive_path)?,
                     entry.data_start(),
@@ -188,25 +292,31 @@ impl ZipEntry {
                 )?;
                 adder.add_content(Box::new(reader), jbk::creator::CompHint::Detect)?
             } else {
-                let mut data = vec![];
-                entry.read_to_end(&mut data)?;
-                adder.add_content(
-                    Box::new(std::io::Cursor::new(data)),
-                    jbk::creator::CompHint::Detect,
-                )?
+                let (content, _, file_checksum) = arx::create::read_content(
+                    &mut entry,
+                    size,
+                    options.spill_threshold,
+                    options.checksum,
+                )?;
+                checksum = file_checksum;
+                adder.add_content(content, jbk::creator::CompHint::Detect)?
             };
-            Self {
-                path,
-                kind: arx::create::EntryKind::File(entry.size().into(), content_address),
-                mtime,
-                mode,
-            }
+            arx::create::EntryKind::File(size.into(), content_address)
+        };
+        Ok(Self {
+            path,
+            kind,
+            uid,
+            gid,
+            mode,
+            mtime,
+            checksum,
         })
     }
 }
 
 impl arx::create::EntryTrait for ZipEntry {
-    fn kind(&self) -> jbk::Result<Option<arx::create::EntryKind>> {
+    fn kind(&self) -> arx::Result<Option<arx::create::EntryKind>> {
         Ok(Some(self.kind.clone()))
     }
     fn path(&self) -> &arx::Path {
@@ -214,10 +324,10 @@ impl arx::create::EntryTrait for ZipEntry {
     }
 
     fn uid(&self) -> u64 {
-        0
+        self.uid
     }
     fn gid(&self) -> u64 {
-        0
+        self.gid
     }
     fn mode(&self) -> u64 {
         self.mode
@@ -225,6 +335,9 @@ impl arx::create::EntryTrait for ZipEntry {
     fn mtime(&self) -> u64 {
         self.mtime
     }
+    fn checksum(&self) -> Option<[u8; 32]> {
+        self.checksum
+    }
 }
 
 impl<R: Read + Seek> Converter<R> {
@@ -233,6 +346,8 @@ impl<R: Read + Seek> Converter<R> {
         archive_path: PathBuf,
         outfile: P,
         concat_mode: jbk::creator::ConcatMode,
+        compression: jbk::creator::Compression,
+        options: ConvertOptions,
     ) -> jbk::Result<Self> {
         let progress = Arc::new(ProgressBar::new(&archive)?);
         let arx_creator = arx::create::SimpleCreator::new(
@@ -240,7 +355,7 @@ impl<R: Read + Seek> Converter<R> {
             concat_mode,
             Arc::clone(&progress) as Arc<dyn jbk::creator::Progress>,
             Rc::new(()),
-            jbk::creator::Compression::zstd(),
+            compression,
         )?;
 
         Ok(Self {
@@ -248,21 +363,27 @@ impl<R: Read + Seek> Converter<R> {
             archive,
             archive_path,
             progress,
+            options,
         })
     }
 
-    fn finalize(self, outfile: &Path) -> jbk::Result<()> {
+    fn finalize(self, outfile: &Path) -> arx::Result<()> {
         self.arx_creator.finalize(outfile)
     }
 
     pub fn run(mut self, outfile: &Path) -> jbk::Result<()> {
         for idx in 0..self.archive.len() {
             self.progress.entries.inc(1);
-            let entry = self.archive.by_index(idx).unwrap();
-            let entry = ZipEntry::new(entry, self.arx_creator.adder(), &self.archive_path)?;
+            let entry = self.archive.by_index(idx).map_err(std::io::Error::from)?;
+            let entry = ZipEntry::new(
+                entry,
+                self.arx_creator.adder(),
+                &self.archive_path,
+                &self.options,
+            )?;
             self.arx_creator.add_entry(&entry)?;
         }
-        self.finalize(outfile)
+        Ok(self.finalize(outfile)?)
     }
 }
 
@@ -294,7 +415,7 @@ fn main() -> jbk::Result<()> {
     }
 
     let file = std::fs::File::open(args.zip_file.as_ref().unwrap())?;
-    let archive = zip::ZipArchive::new(file).unwrap();
+    let archive = zip::ZipArchive::new(file).map_err(std::io::Error::from)?;
     let converter = Converter::new(
         archive,
         args.zip_file.unwrap(),
@@ -303,6 +424,232 @@ fn main() -> jbk::Result<()> {
             None => jbk::creator::ConcatMode::OneFile,
             Some(e) => e.into(),
         },
+        args.compression,
+        ConvertOptions {
+            checksum: args.checksum,
+            spill_threshold: args.spill_threshold,
+        },
     )?;
     converter.run(args.outfile.as_ref().unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arx::CommonEntry;
+
+    fn extra_field(id: u16, data: &[u8]) -> Vec<u8> {
+        let mut field = id.to_le_bytes().to_vec();
+        field.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        field.extend_from_slice(data);
+        field
+    }
+
+    fn unix_field(uid: &[u8], gid: &[u8]) -> Vec<u8> {
+        let mut data = vec![1, uid.len() as u8];
+        data.extend_from_slice(uid);
+        data.push(gid.len() as u8);
+        data.extend_from_slice(gid);
+        extra_field(INFOZIP_UNIX_ID, &data)
+    }
+
+    #[test]
+    fn test_read_le() {
+        assert_eq!(read_le(&[]), Some(0));
+        assert_eq!(read_le(&[0x34, 0x12]), Some(0x1234));
+        assert_eq!(read_le(&[0x78, 0x56, 0x34, 0x12]), Some(0x12345678));
+        assert_eq!(read_le(&[0xff; 8]), Some(u64::MAX));
+        assert_eq!(read_le(&[0; 9]), None);
+    }
+
+    #[test]
+    fn test_parse_owner() {
+        let unix = unix_field(&1000u32.to_le_bytes(), &100_000u32.to_le_bytes());
+        let old_unix = extra_field(INFOZIP_UNIX_OLD_ID, &[0x34, 0x12, 0x78, 0x56]);
+        let timestamp = extra_field(0x5455, &[1, 0, 0, 0, 0]);
+
+        assert_eq!(parse_owner(&[]), None);
+        assert_eq!(parse_owner(&timestamp), None);
+        assert_eq!(parse_owner(&unix), Some((1000, 100_000)));
+        assert_eq!(parse_owner(&old_unix), Some((0x1234, 0x5678)));
+        assert_eq!(
+            parse_owner(&[timestamp.as_slice(), &unix].concat()),
+            Some((1000, 100_000))
+        );
+        // Sizes of ids may vary.
+        assert_eq!(
+            parse_owner(&unix_field(&[5], &u64::MAX.to_le_bytes())),
+            Some((5, u64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_parse_owner_precedence() {
+        let unix = unix_field(&1000u32.to_le_bytes(), &2000u32.to_le_bytes());
+        let old_unix = extra_field(INFOZIP_UNIX_OLD_ID, &[1, 0, 2, 0]);
+        // 0x7875 wins over 0x7855, whatever the order.
+        assert_eq!(
+            parse_owner(&[old_unix.as_slice(), &unix].concat()),
+            Some((1000, 2000))
+        );
+        assert_eq!(
+            parse_owner(&[unix.as_slice(), &old_unix].concat()),
+            Some((1000, 2000))
+        );
+        // 0x7855 without uid/gid (as in the central directory) is ignored.
+        assert_eq!(parse_owner(&extra_field(INFOZIP_UNIX_OLD_ID, &[])), None);
+    }
+
+    #[test]
+    fn test_parse_owner_unknown_version() {
+        let mut data = vec![2, 4];
+        data.extend_from_slice(&1000u32.to_le_bytes());
+        data.push(4);
+        data.extend_from_slice(&2000u32.to_le_bytes());
+        let unix_v2 = extra_field(INFOZIP_UNIX_ID, &data);
+        let old_unix = extra_field(INFOZIP_UNIX_OLD_ID, &[1, 0, 2, 0]);
+        assert_eq!(parse_owner(&unix_v2), None);
+        assert_eq!(
+            parse_owner(&[unix_v2.as_slice(), &old_unix].concat()),
+            Some((1, 2))
+        );
+    }
+
+    #[test]
+    fn test_parse_owner_truncated() {
+        let unix = unix_field(&1000u32.to_le_bytes(), &2000u32.to_le_bytes());
+        let old_unix = extra_field(INFOZIP_UNIX_OLD_ID, &[1, 0, 2, 0]);
+
+        // Field shorter than its header says.
+        let truncated = &unix[..unix.len() - 1];
+        assert_eq!(parse_owner(truncated), None);
+        assert_eq!(
+            parse_owner(&[old_unix.as_slice(), truncated].concat()),
+            Some((1, 2))
+        );
+        // Less than a header.
+        assert_eq!(
+            parse_owner(&[old_unix.as_slice(), &[0x75, 0x78, 11]].concat()),
+            Some((1, 2))
+        );
+
+        // Ids bigger than the field.
+        let mut bad_sizes = vec![];
+        for data in [
+            [1, 4, 0xe8, 0x03].as_slice(),
+            &[1, 2, 0xe8, 0x03, 4, 0xd0],
+            &[1, 2, 0xe8, 0x03],
+            &[1],
+        ] {
+            bad_sizes.push(extra_field(INFOZIP_UNIX_ID, data));
+        }
+        // Ids of more than 8 bytes.
+        bad_sizes.push(unix_field(&[0; 9], &[0; 4]));
+        for bad_size in bad_sizes {
+            assert_eq!(parse_owner(&bad_size), None, "{bad_size:?}");
+            assert_eq!(
+                parse_owner(&[old_unix.as_slice(), &bad_size].concat()),
+                Some((1, 2)),
+                "{bad_size:?}"
+            );
+        }
+    }
+
+    /// A zip created by `arx export` keeps symlinks, owner and group when converted back.
+    #[test]
+    fn test_roundtrip_export() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+
+        let source = tmp_dir.path().join("source.arx");
+        let mut creator = arx::create::SimpleCreator::new(
+            &source,
+            jbk::creator::ConcatMode::OneFile,
+            Arc::new(()),
+            Rc::new(()),
+            jbk::creator::Compression::None,
+        )
+        .unwrap();
+        let content = creator
+            .adder()
+            .add_content(
+                Box::new(std::io::Cursor::new(b"content".to_vec())),
+                jbk::creator::CompHint::Detect,
+            )
+            .unwrap();
+        for (path, kind, mode) in [
+            (
+                "file.txt",
+                arx::create::EntryKind::File(7u64.into(), content),
+                0o640,
+            ),
+            (
+                "link",
+                arx::create::EntryKind::Link("file.txt".into()),
+                0o777,
+            ),
+        ] {
+            let entry = ZipEntry {
+                path: path.into(),
+                kind,
+                uid: 1000,
+                gid: 100_000,
+                mode,
+                mtime: 1_000_000_000,
+                checksum: None,
+            };
+            creator.add_entry(&entry).unwrap();
+        }
+        creator.finalize(&source).unwrap();
+
+        let zip_file = tmp_dir.path().join("export.zip");
+        arx::export::export_zip(
+            &arx::Arx::new(&source).unwrap(),
+            None,
+            std::fs::File::create(&zip_file).unwrap(),
+        )
+        .unwrap();
+
+        let converted = tmp_dir.path().join("converted.arx");
+        let archive = zip::ZipArchive::new(std::fs::File::open(&zip_file).unwrap()).unwrap();
+        Converter::new(
+            archive,
+            zip_file,
+            &converted,
+            jbk::creator::ConcatMode::OneFile,
+            jbk::creator::Compression::None,
+            ConvertOptions {
+                checksum: false,
+                spill_threshold: 1024,
+            },
+        )
+        .unwrap()
+        .run(&converted)
+        .unwrap();
+
+        let arx = arx::Arx::new(&converted).unwrap();
+        match arx
+            .get_entry::<arx::FullBuilder>(arx::Path::new("link"))
+            .unwrap()
+        {
+            arx::Entry::Link(link) => {
+                assert_eq!(link.target().as_slice(), b"file.txt");
+                assert_eq!(link.owner(), 1000);
+                assert_eq!(link.group(), 100_000);
+                assert_eq!(link.mtime(), 1_000_000_000);
+            }
+            _ => panic!("link must be a link"),
+        }
+        match arx
+            .get_entry::<arx::FullBuilder>(arx::Path::new("file.txt"))
+            .unwrap()
+        {
+            arx::Entry::File(file) => {
+                assert_eq!(file.owner(), 1000);
+                assert_eq!(file.group(), 100_000);
+                assert_eq!(file.rights(), 0o640);
+                assert_eq!(file.size().into_u64(), 7);
+            }
+            _ => panic!("file.txt must be a file"),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use arx::CommonEntry;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Header id of the Info-ZIP unix extra field.
+const INFOZIP_UNIX_ID: u16 = 0x7875;
+
+/// Header id of the extended timestamp extra field.
+const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+
+fn unix_field(uid: u32, gid: u32) -> Box<[u8]> {
+    let mut data = vec![1u8, 4];
+    data.extend_from_slice(&uid.to_le_bytes());
+    data.push(4);
+    data.extend_from_slice(&gid.to_le_bytes());
+    data.into_boxed_slice()
+}
+
+fn timestamp_field(mtime: i32) -> Box<[u8]> {
+    let mut data = vec![1u8];
+    data.extend_from_slice(&mtime.to_le_bytes());
+    data.into_boxed_slice()
+}
+
+fn read_content(arx: &arx::Arx, file: &arx::FileEntry) -> Vec<u8> {
+    let mut content = vec![];
+    match arx.get_bytes(file.content()).unwrap() {
+        jbk::reader::MayMissPack::FOUND(bytes) => {
+            bytes.stream().read_to_end(&mut content).unwrap();
+        }
+        jbk::reader::MayMissPack::MISSING(_) => panic!("Content must be in the archive"),
+    }
+    content
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_convert() {
+    let tmp_dir = tempfile::tempdir_in(Path::new(env!("CARGO_TARGET_TMPDIR")))
+        .expect("Creating tempdir should work");
+    let zip_file = tmp_dir.path().join("test.zip");
+
+    let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_file).unwrap());
+    let base_options = zip::write::FullFileOptions::default()
+        .last_modified_time(zip::DateTime::from_date_and_time(2020, 1, 2, 3, 4, 6).unwrap());
+
+    let mut options = base_options.clone().unix_permissions(0o750);
+    options
+        .add_extra_data(INFOZIP_UNIX_ID, unix_field(1000, 2000), false)
+        .unwrap();
+    writer.add_directory("dir", options).unwrap();
+
+    // Deflated, bigger than the spill threshold.
+    let mut options = base_options
+        .clone()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o640);
+    options
+        .add_extra_data(INFOZIP_UNIX_ID, unix_field(1000, 2000), false)
+        .unwrap();
+    writer.start_file("dir/file.txt", options).unwrap();
+    writer.write_all(b"deflated content").unwrap();
+
+    // Stored with zip64 extra fields.
+    let options = base_options
+        .clone()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+    writer.start_file("dir/big.txt", options).unwrap();
+    writer.write_all(b"zip64 content").unwrap();
+
+    // Symlink with an owner and a time before 1970.
+    let mut options = base_options.clone();
+    options
+        .add_extra_data(INFOZIP_UNIX_ID, unix_field(1001, 2001), false)
+        .unwrap();
+    options
+        .add_extra_data(EXTENDED_TIMESTAMP_ID, timestamp_field(-86400), false)
+        .unwrap();
+    writer.add_symlink("dir/link", "file.txt", options).unwrap();
+    writer.finish().unwrap();
+
+    let arx_file = tmp_dir.path().join("test.arx");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_zip2arx"))
+        .arg(&zip_file)
+        .arg("--outfile")
+        .arg(&arx_file)
+        .args(["--compression", "zstd=3", "--spill-threshold", "4"])
+        .output()
+        .expect("Launching zip2arx should work.");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let arx = arx::Arx::new(&arx_file).unwrap();
+    match arx
+        .get_entry::<arx::FullBuilder>(arx::Path::new("dir"))
+        .unwrap()
+    {
+        arx::Entry::Dir(_, dir) => {
+            assert_eq!(dir.owner(), 1000);
+            assert_eq!(dir.group(), 2000);
+            assert_eq!(dir.rights(), 0o750);
+        }
+        _ => panic!("dir must be a directory"),
+    }
+    match arx
+        .get_entry::<arx::FullBuilder>(arx::Path::new("dir/file.txt"))
+        .unwrap()
+    {
+        arx::Entry::File(file) => {
+            assert_eq!(file.owner(), 1000);
+            assert_eq!(file.group(), 2000);
+            assert_eq!(file.rights(), 0o640);
+            assert_eq!(file.size().into_u64(), 16);
+            assert_eq!(read_content(&arx, &file), b"deflated content");
+        }
+        _ => panic!("file.txt must be a file"),
+    }
+    match arx
+        .get_entry::<arx::FullBuilder>(arx::Path::new("dir/big.txt"))
+        .unwrap()
+    {
+        arx::Entry::File(file) => {
+            assert_eq!(file.size().into_u64(), 13);
+            assert_eq!(read_content(&arx, &file), b"zip64 content");
+        }
+        _ => panic!("big.txt must be a file"),
+    }
+    match arx
+        .get_entry::<arx::FullBuilder>(arx::Path::new("dir/link"))
+        .unwrap()
+    {
+        arx::Entry::Link(link) => {
+            assert_eq!(link.target().as_slice(), b"file.txt");
+            assert_eq!(link.owner(), 1001);
+            assert_eq!(link.group(), 2001);
+            assert_eq!(link.mtime(), -86400i64 as u64);
+        }
+        _ => panic!("link must be a link"),
+    }
+}